@@ -0,0 +1,119 @@
+use std::{fs, path::Path};
+
+use crate::project_type::ProjectType;
+
+/// Minimum share of source bytes a language needs to be picked
+const MIN_SHARE: f64 = 0.15;
+
+/// Files and bytes counted for one language
+struct Entry {
+    lang: ProjectType,
+    exts: Vec<String>,
+    files: usize,
+    bytes: u64,
+}
+
+/// Guesses project types from file extension statistics when no marker
+/// files were found. Prints the census so the choice can be checked, unless
+/// `quiet`. Returns `None` when no language has enough of a share to go on.
+pub fn detect(files: &[String], quiet: bool) -> Option<Vec<ProjectType>> {
+    let census = tally(files);
+    if census.is_empty() {
+        return None;
+    }
+    let picked: Vec<&(Entry, f64)> = census.iter().filter(|(_, s)| *s >= MIN_SHARE).collect();
+    if quiet {
+        return (!picked.is_empty()).then(|| picked.iter().map(|(e, _)| e.lang).collect());
+    }
+    println!("No project files found, language census:");
+    for (e, share) in &census {
+        println!(
+            "  {:<12} {:>5} files {:>10} {:>4.0}%  ({})",
+            e.lang.to_string(),
+            e.files,
+            format_size(e.bytes),
+            share * 100.0,
+            e.exts.join(" ")
+        );
+    }
+    if picked.is_empty() {
+        println!("No language stands out");
+        return None;
+    }
+    println!(
+        "Guessed project type(s): {}",
+        picked
+            .iter()
+            .map(|(e, share)| format!("{} ({:.0}% confidence)", e.lang, share * 100.0))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    Some(picked.iter().map(|(e, _)| e.lang).collect())
+}
+
+/// Counts files and bytes per language, paired with each language's share
+/// and sorted by it
+fn tally(files: &[String]) -> Vec<(Entry, f64)> {
+    let mut census: Vec<Entry> = Vec::new();
+    for file in files {
+        let path = Path::new(file);
+        let Some(ext) = path.extension().map(|e| e.to_string_lossy()) else {
+            continue;
+        };
+        let Some(lang) = ProjectType::from_extension(&ext) else {
+            continue;
+        };
+        let bytes = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        let pos = match census.iter().position(|e| e.lang == lang) {
+            Some(pos) => pos,
+            None => {
+                census.push(Entry {
+                    lang,
+                    exts: Vec::new(),
+                    files: 0,
+                    bytes: 0,
+                });
+                census.len() - 1
+            }
+        };
+        let entry = &mut census[pos];
+        let ext = format!(".{}", ext);
+        if !entry.exts.contains(&ext) {
+            entry.exts.push(ext);
+        }
+        entry.files += 1;
+        entry.bytes += bytes;
+    }
+    // weigh by size, falling back to file counts when sizes are unknown
+    let total_bytes: u64 = census.iter().map(|e| e.bytes).sum();
+    let total_files: usize = census.iter().map(|e| e.files).sum();
+    let mut census: Vec<(Entry, f64)> = census
+        .into_iter()
+        .map(|e| {
+            let share = if total_bytes > 0 {
+                e.bytes as f64 / total_bytes as f64
+            } else {
+                e.files as f64 / total_files as f64
+            };
+            (e, share)
+        })
+        .collect();
+    census.sort_by(|a, b| b.1.total_cmp(&a.1));
+    census
+}
+
+/// Formats a byte count for humans, e.g. `12.3 KiB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
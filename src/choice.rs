@@ -1,4 +1,10 @@
-use std::{io::Write, process::exit};
+use std::{fs, io::Write, path::Path, process::exit};
+
+use crate::{
+    census::format_size,
+    pattern::Pattern,
+    picker::{self, Item, Preset},
+};
 
 #[derive(Default)]
 pub enum ScanType {
@@ -6,8 +12,8 @@ pub enum ScanType {
     #[default]
     Auto,
 }
-pub fn get_types() -> Vec<String> {
-    print!("\nWhich file formats to include? (example: rs toml json) ");
+pub fn get_types() -> Vec<Pattern> {
+    print!("\nWhich file formats to include? (example: rs toml Cargo.lock name:Makefile) ");
     std::io::stdout().flush().expect("Could not flush stdin");
     let mut buf = String::new();
     std::io::stdin()
@@ -18,19 +24,78 @@ pub fn get_types() -> Vec<String> {
         println!("No file formats specified, exiting...");
         exit(1)
     }
-    let buf: Vec<String> = buf
-        .split(' ')
-        .map(|val| val.trim().to_string())
-        .map(|val| {
-            if val.starts_with('.') {
-                val
-            } else {
-                format!(".{}", val)
+    buf.split_whitespace().map(Pattern::from_user).collect()
+}
+
+/// Lets the user tick file types among those present in `files`, with their
+/// file counts and sizes. `presets` are named pattern sets, such as the
+/// detected project types, ticking every matching file type at once.
+pub fn pick_types(files: &[String], presets: &[(String, Vec<Pattern>)]) -> Vec<Pattern> {
+    // files without an extension are listed by name, e.g. Makefile
+    let mut groups: Vec<(Pattern, Vec<&String>, u64)> = Vec::new();
+    for file in files {
+        let path = Path::new(file);
+        let pattern = match path.extension() {
+            Some(ext) => Pattern::Extension(ext.to_string_lossy().to_lowercase()),
+            None => Pattern::Name(
+                path.file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            ),
+        };
+        let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        match groups.iter_mut().find(|(p, _, _)| *p == pattern) {
+            Some((_, members, bytes)) => {
+                members.push(file);
+                *bytes += size;
             }
+            None => groups.push((pattern, vec![file], size)),
+        }
+    }
+    groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(b.2.cmp(&a.2)));
+
+    let mut items: Vec<Item> = groups
+        .iter()
+        .map(|(pattern, members, bytes)| Item {
+            label: format!(
+                "{:<24} {:>5} files {:>10}",
+                pattern.to_string(),
+                members.len(),
+                format_size(*bytes)
+            ),
+            ticked: false,
+        })
+        .collect();
+    let presets: Vec<Preset> = presets
+        .iter()
+        .map(|(name, patterns)| Preset {
+            name: name.clone(),
+            items: groups
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, members, _))| {
+                    members
+                        .iter()
+                        .any(|f| patterns.iter().any(|p| p.matches(f)))
+                })
+                .map(|(i, _)| i)
+                .collect(),
         })
         .collect();
 
-    buf
+    let confirmed = picker::pick("Which file types to include?", &mut items, &presets)
+        .expect("Unable to drive the terminal");
+    let picked: Vec<Pattern> = groups
+        .into_iter()
+        .zip(items)
+        .filter(|(_, item)| item.ticked)
+        .map(|((pattern, _, _), _)| pattern)
+        .collect();
+    if !confirmed || picked.is_empty() {
+        println!("No file formats specified, exiting...");
+        exit(1)
+    }
+    picked
 }
 
 pub fn get_ignore() -> Vec<String> {
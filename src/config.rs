@@ -0,0 +1,91 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+/// Name of the per-project configuration file, looked up in the target directory
+const LOCAL_CONFIG: &str = ".reatler.toml";
+
+/// Settings read from the user and project configuration files
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Project types to add, or built-in ones to override
+    #[serde(rename = "type")]
+    pub types: Vec<TypeConfig>,
+    /// Workspaces smart mode searches when no directory is given
+    #[serde(rename = "root")]
+    pub roots: Vec<RootConfig>,
+}
+
+/// A project type definition as written in the configuration
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TypeConfig {
+    pub name: String,
+    /// Append to a built-in type of the same name instead of replacing its lists
+    pub extend: bool,
+    pub markers: Option<Vec<String>>,
+    pub include: Option<Vec<String>>,
+    pub ignore: Option<Vec<String>>,
+    /// Language tag for the code fence wrapped around this type's files
+    pub fence: Option<String>,
+}
+
+/// A smart-mode search root as written in the configuration
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RootConfig {
+    /// Directory to search, `~/` standing for the home directory
+    pub path: String,
+    /// Substrings of paths to leave out under this root
+    pub ignore: Vec<String>,
+    pub max_depth: Option<usize>,
+}
+
+impl RootConfig {
+    /// `path` with a leading `~/` expanded to the home directory
+    pub fn expanded_path(&self) -> PathBuf {
+        match (self.path.strip_prefix("~/"), env::var_os("HOME")) {
+            (Some(rest), Some(home)) => Path::new(&home).join(rest),
+            _ => PathBuf::from(&self.path),
+        }
+    }
+}
+
+/// Path of the user configuration: `$REATLER_CONFIG`, otherwise
+/// `reatler/config.toml` under `$XDG_CONFIG_HOME` or `~/.config`
+pub fn user_config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("REATLER_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|h| Path::new(&h).join(".config")))?;
+    Some(base.join("reatler").join("config.toml"))
+}
+
+/// Loads the user configuration followed by `.reatler.toml` in `dir`; project
+/// settings come last so they win over user ones
+pub fn load(dir: &str) -> io::Result<Config> {
+    let mut config = Config::default();
+    let paths = user_config_path()
+        .into_iter()
+        .chain([Path::new(dir).join(LOCAL_CONFIG)]);
+    for path in paths {
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let file: Config = toml::from_str(&text).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })?;
+        config.types.extend(file.types);
+        config.roots.extend(file.roots);
+    }
+    Ok(config)
+}
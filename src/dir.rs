@@ -1,8 +1,56 @@
-use std::{fs::read_dir, path::Path};
-#[derive(Default)]
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{
+    pattern::Pattern,
+    walk::{walk, Symlinks},
+};
 pub struct ScanParams {
     pub ignore: Vec<String>,
-    pub include: Vec<String>,
+    /// Directory names skipped wherever they appear, e.g. `node_modules`
+    pub skip_dirs: Vec<String>,
+    pub include: Vec<Pattern>,
+    /// Include patterns limited to a sub-project
+    pub scopes: Vec<Scope>,
+    pub symlinks: Symlinks,
+    /// How many levels below the root files may be, `Some(1)` meaning only
+    /// its direct children
+    pub max_depth: Option<usize>,
+    /// Keep files and directories whose name starts with a dot
+    pub include_hidden: bool,
+    /// Size bounds in bytes, inclusive
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Modification time bounds
+    pub modified_since: Option<SystemTime>,
+    pub modified_before: Option<SystemTime>,
+}
+
+impl Default for ScanParams {
+    fn default() -> Self {
+        ScanParams {
+            ignore: Vec::new(),
+            skip_dirs: Vec::new(),
+            include: Vec::new(),
+            scopes: Vec::new(),
+            symlinks: Symlinks::default(),
+            max_depth: None,
+            include_hidden: true,
+            min_size: None,
+            max_size: None,
+            modified_since: None,
+            modified_before: None,
+        }
+    }
+}
+
+/// Include patterns that only apply to files below `root`
+pub struct Scope {
+    pub root: PathBuf,
+    pub include: Vec<Pattern>,
 }
 /// Scans a directory for files that match the include and ignore parameters
 pub fn scan_dir(
@@ -10,36 +58,78 @@ pub fn scan_dir(
     params: ScanParams,
     recursive: bool,
 ) -> std::io::Result<Vec<String>> {
-    let mut files: Vec<String> = Vec::new();
-    dir_helper(dirname, &mut files, &params, recursive)?;
-    Ok(files)
+    let max_depth = if recursive { params.max_depth } else { Some(1) };
+    let skip = |path: &Path, is_dir: bool| {
+        is_ignored(&path.to_string_lossy(), &params.ignore)
+            || (is_dir
+                && is_skipped_dir(
+                    path.strip_prefix(dirname).unwrap_or(path),
+                    &params.skip_dirs,
+                ))
+            || (!params.include_hidden && is_hidden(path))
+    };
+    Ok(walk(dirname, max_depth, params.symlinks, &skip)?
+        .into_iter()
+        .filter(|e| !e.is_dir && is_wanted(&e.path, &params) && fits_limits(&e.path, &params))
+        .map(|e| e.path)
+        .collect())
 }
 
-fn dir_helper(
-    dirname: &str,
-    files: &mut Vec<String>,
-    params: &ScanParams,
-    recursive: bool,
-) -> std::io::Result<()> {
-    let ScanParams { include, ignore } = params;
-    for entry in read_dir(dirname)? {
-        let entry = entry?;
-        let path = entry.path();
-        let is_dir = path.is_dir();
-        let path_str = path.to_string_lossy().to_string();
+/// Applies the include, ignore and limit parameters to an already known list
+/// of files below `root`
+pub fn filter_files(root: &str, files: Vec<String>, params: &ScanParams) -> Vec<String> {
+    files
+        .into_iter()
+        .filter(|f| {
+            let path = Path::new(f);
+            let rel = path.strip_prefix(root).unwrap_or(path);
+            let parent = rel.parent().unwrap_or(Path::new(""));
+            !is_ignored(f, &params.ignore)
+                && !is_skipped_dir(parent, &params.skip_dirs)
+                && params
+                    .max_depth
+                    .is_none_or(|max| rel.components().count() <= max)
+                && (params.include_hidden || !rel.components().any(|c| is_hidden(c.as_ref())))
+                && is_wanted(f, params)
+                && fits_limits(f, params)
+        })
+        .collect()
+}
 
-        if is_ignored(&path_str, ignore) {
-            continue;
-        }
+/// Checks whether the last component of `path` is a dotfile or dot-directory
+fn is_hidden(path: &Path) -> bool {
+    matches!(path.components().next_back(), Some(Component::Normal(name)) if name.to_string_lossy().starts_with('.'))
+}
 
-        if is_dir && recursive {
-            dir_helper(&path_str, files, params, true)?;
-        } else if is_included(&path_str, include) {
-            files.push(path_str);
-        }
+/// Checks the size and modification time bounds. Files whose metadata can't
+/// be read only pass when no bound is set.
+fn fits_limits(path: &str, params: &ScanParams) -> bool {
+    let bounded = params.min_size.is_some()
+        || params.max_size.is_some()
+        || params.modified_since.is_some()
+        || params.modified_before.is_some();
+    if !bounded {
+        return true;
     }
+    let Ok(meta) = fs::metadata(path) else {
+        return false;
+    };
+    let modified = meta.modified().ok();
+    params.min_size.is_none_or(|min| meta.len() >= min)
+        && params.max_size.is_none_or(|max| meta.len() <= max)
+        && params
+            .modified_since
+            .is_none_or(|t| modified.is_some_and(|m| m >= t))
+        && params
+            .modified_before
+            .is_none_or(|t| modified.is_some_and(|m| m < t))
+}
 
-    Ok(())
+/// Checks whether any component of `dir`, relative to the scan root, is one
+/// of the `skip` directory names
+fn is_skipped_dir(dir: &Path, skip: &[String]) -> bool {
+    dir.components()
+        .any(|c| skip.iter().any(|s| c.as_os_str() == s.as_str()))
 }
 
 /// Normalize a gitignore pattern to handle different path formats
@@ -51,7 +141,7 @@ fn normalize_pattern(pattern: &str) -> String {
 }
 
 /// Checks if a file should be ignored based on gitignore patterns
-fn is_ignored(path: &str, ignored: &[String]) -> bool {
+pub fn is_ignored(path: &str, ignored: &[String]) -> bool {
     let path = Path::new(path);
     let path_str = path.to_string_lossy();
 
@@ -74,15 +164,16 @@ fn is_ignored(path: &str, ignored: &[String]) -> bool {
 
     false
 }
-/// Updated is_included to match against the full path
-fn is_included(path: &str, included: &[String]) -> bool {
-    let path = Path::new(path);
-    if let Some(file_name) = path.file_name() {
-        let file_name = file_name.to_string_lossy();
-        included
+/// Checks the global include patterns and those of every scope containing `path`
+fn is_wanted(path: &str, params: &ScanParams) -> bool {
+    is_included(path, &params.include)
+        || params
+            .scopes
             .iter()
-            .any(|included| file_name.ends_with(included))
-    } else {
-        false
-    }
+            .any(|s| Path::new(path).starts_with(&s.root) && is_included(path, &s.include))
+}
+
+/// Checks if a file matches any of the include patterns
+fn is_included(path: &str, included: &[Pattern]) -> bool {
+    included.iter().any(|p| p.matches(path))
 }
@@ -0,0 +1,145 @@
+use std::fmt;
+
+use crate::pattern::Pattern;
+
+/// Application frameworks detected on top of a project's language
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Framework {
+    Django,
+    Rails,
+    NextJs,
+    Spring,
+    Laravel,
+    Flutter,
+    Phoenix,
+}
+
+impl fmt::Display for Framework {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Framework::Django => "Django",
+            Framework::Rails => "Rails",
+            Framework::NextJs => "Next.js",
+            Framework::Spring => "Spring",
+            Framework::Laravel => "Laravel",
+            Framework::Flutter => "Flutter",
+            Framework::Phoenix => "Phoenix",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Framework {
+    pub const ALL: [Self; 7] = [
+        Self::Django,
+        Self::Rails,
+        Self::NextJs,
+        Self::Spring,
+        Self::Laravel,
+        Self::Flutter,
+        Self::Phoenix,
+    ];
+
+    /// Frameworks used by a project. `has` tells whether a file exists in the
+    /// project root, `read` returns its contents (empty when missing).
+    pub fn detect(has: impl Fn(&str) -> bool, read: impl Fn(&str) -> String) -> Vec<Self> {
+        let depends = |files: &[&str], needle: &str| {
+            files
+                .iter()
+                .filter(|f| has(f))
+                .any(|f| read(f).to_lowercase().contains(needle))
+        };
+        Self::ALL
+            .into_iter()
+            .filter(|fw| match fw {
+                Self::Django => {
+                    has("manage.py")
+                        || depends(
+                            &["requirements.txt", "pyproject.toml", "Pipfile", "setup.py"],
+                            "django",
+                        )
+                }
+                Self::Rails => {
+                    has("bin/rails")
+                        || depends(&["Gemfile"], "gem \"rails\"")
+                        || depends(&["Gemfile"], "gem 'rails'")
+                }
+                Self::NextJs => {
+                    ["next.config.js", "next.config.mjs", "next.config.ts"]
+                        .iter()
+                        .any(|f| has(f))
+                        || depends(&["package.json"], "\"next\"")
+                }
+                Self::Spring => depends(
+                    &["pom.xml", "build.gradle", "build.gradle.kts"],
+                    "org.springframework",
+                ),
+                Self::Laravel => has("artisan") || depends(&["composer.json"], "laravel/framework"),
+                Self::Flutter => depends(&["pubspec.yaml"], "sdk: flutter"),
+                Self::Phoenix => depends(&["mix.exs"], ":phoenix"),
+            })
+            .collect()
+    }
+
+    /// Include patterns for the views, routes and migrations the language's own
+    /// patterns miss
+    pub fn get_files(&self) -> Vec<Pattern> {
+        self.file_patterns()
+            .into_iter()
+            .map(Pattern::parse)
+            .collect()
+    }
+
+    fn file_patterns(&self) -> Vec<&'static str> {
+        match self {
+            Self::Django => vec![
+                ".html",  // templates
+                ".jinja", // Jinja templates
+                ".jinja2",
+                ".j2",
+                "manage.py",
+            ],
+            Self::Rails => vec![
+                ".erb",                  // views
+                ".haml",                 // views
+                ".slim",                 // views
+                ".jbuilder",             // JSON views
+                ".rake",                 // tasks
+                "config.ru",             // Rack entry point
+                "config/*.yml",          // database, storage, locales
+                "glob:db/structure.sql", // schema dump
+            ],
+            Self::NextJs => vec![
+                "next.config.js",
+                "next.config.mjs",
+                "next.config.ts",
+                ".mdx",        // MDX pages
+                ".module.css", // CSS modules
+            ],
+            Self::Spring => vec![
+                "application*.properties", // Spring Boot configuration
+                "application*.yml",
+                "application*.yaml",
+                "templates/**/*.html",   // Thymeleaf views
+                "db/migration/**/*.sql", // Flyway migrations
+            ],
+            Self::Laravel => vec![
+                ".blade.php", // views
+                "artisan",    // console entry point
+                "routes/*.php",
+                "database/migrations/*.php",
+            ],
+            Self::Flutter => vec![
+                "analysis_options.yaml",
+                "l10n.yaml",
+                ".arb", // localisation
+            ],
+            Self::Phoenix => vec![
+                ".heex", // HEEx templates
+                ".eex",  // EEx templates
+                ".leex", // LiveView templates
+                ".sface",
+            ],
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    env, fs, io,
+    path::{self, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How often and how recently a directory was chosen in smart mode
+struct Visit {
+    path: PathBuf,
+    count: u32,
+    last: u64,
+}
+
+/// Directories previously chosen in smart mode, zoxide-style
+#[derive(Default)]
+pub struct Frecency {
+    visits: Vec<Visit>,
+}
+
+/// Location of the database: `reatler/frecency` under `$XDG_DATA_HOME` or
+/// `~/.local/share`
+fn db_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|h| Path::new(&h).join(".local/share")))?;
+    Some(base.join("reatler").join("frecency"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl Frecency {
+    /// Reads the database, starting empty when it is missing or unreadable.
+    /// Each line holds `count<TAB>last visit in unix seconds<TAB>path`.
+    pub fn load() -> Self {
+        let Some(text) = db_path().and_then(|p| fs::read_to_string(p).ok()) else {
+            return Self::default();
+        };
+        let visits = text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                Some(Visit {
+                    count: fields.next()?.parse().ok()?,
+                    last: fields.next()?.parse().ok()?,
+                    path: PathBuf::from(fields.next()?),
+                })
+            })
+            .collect();
+        Frecency { visits }
+    }
+
+    /// Notes that `dir` was just chosen
+    pub fn record(&mut self, dir: &str) {
+        let Ok(path) = path::absolute(dir) else {
+            return;
+        };
+        let now = now();
+        match self.visits.iter_mut().find(|v| v.path == path) {
+            Some(v) => {
+                v.count += 1;
+                v.last = now;
+            }
+            None => self.visits.push(Visit {
+                path,
+                count: 1,
+                last: now,
+            }),
+        }
+    }
+
+    /// Frequency weighted by recency; zero for directories never chosen
+    pub fn score(&self, dir: &str) -> f64 {
+        let Ok(path) = path::absolute(dir) else {
+            return 0.0;
+        };
+        let Some(v) = self.visits.iter().find(|v| v.path == path) else {
+            return 0.0;
+        };
+        let age = now().saturating_sub(v.last);
+        let weight = match age {
+            a if a < 60 * 60 => 4.0,
+            a if a < 24 * 60 * 60 => 2.0,
+            a if a < 7 * 24 * 60 * 60 => 0.5,
+            _ => 0.25,
+        };
+        v.count as f64 * weight
+    }
+
+    /// Writes the database back, forgetting directories that no longer exist
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = db_path() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let text: String = self
+            .visits
+            .iter()
+            .filter(|v| v.path.is_dir())
+            .map(|v| format!("{}\t{}\t{}\n", v.count, v.last, v.path.display()))
+            .collect();
+        fs::write(path, text)
+    }
+}
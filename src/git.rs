@@ -0,0 +1,140 @@
+use std::{io, process::Command};
+
+/// Runs `git` inside `dir` and returns its stdout
+fn git(dir: &str, args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+    Ok(output.stdout)
+}
+
+/// Splits NUL-separated git output (`-z`) into paths
+fn split_paths(out: &[u8]) -> Vec<String> {
+    out.split(|b| *b == 0)
+        .filter(|p| !p.is_empty())
+        .map(|p| String::from_utf8_lossy(p).to_string())
+        .collect()
+}
+
+/// Files modified, added or renamed in the working tree since `base`,
+/// relative to `dir`
+pub fn changed_since(dir: &str, base: &str) -> io::Result<Vec<String>> {
+    let out = git(
+        dir,
+        &[
+            "diff",
+            "--name-only",
+            "--relative",
+            "-z",
+            "--diff-filter=AMR",
+            base,
+            "--",
+        ],
+    )?;
+    Ok(split_paths(&out))
+}
+
+/// Whether `dir` holds uncommitted changes or untracked files
+pub fn is_dirty(dir: &str) -> io::Result<bool> {
+    let out = git(dir, &["status", "--porcelain", "-z", "--", "."])?;
+    Ok(!out.is_empty())
+}
+
+/// Files modified, added or renamed in the index, relative to `dir`
+pub fn staged(dir: &str) -> io::Result<Vec<String>> {
+    let out = git(
+        dir,
+        &[
+            "diff",
+            "--cached",
+            "--name-only",
+            "--relative",
+            "-z",
+            "--diff-filter=AMR",
+            "--",
+        ],
+    )?;
+    Ok(split_paths(&out))
+}
+
+/// A path touched by a diff, relative to the directory it was computed in
+pub enum Change {
+    Present(String),
+    Deleted(String),
+}
+
+/// Unified diff for `range`, which is either a single ref (compared with the
+/// working tree) or a `base..HEAD` style range
+pub fn diff(dir: &str, range: &str) -> io::Result<String> {
+    // user colour and external diff settings must not leak into the bundle
+    let out = git(
+        dir,
+        &[
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "--relative",
+            range,
+            "--",
+        ],
+    )?;
+    Ok(String::from_utf8_lossy(&out).to_string())
+}
+
+/// Files touched by `diff(dir, range)`, with deletions kept apart
+pub fn touched(dir: &str, range: &str) -> io::Result<Vec<Change>> {
+    let out = git(
+        dir,
+        &[
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "--name-status",
+            "--relative",
+            "-z",
+            range,
+            "--",
+        ],
+    )?;
+    let fields = split_paths(&out);
+    let mut changes = Vec::new();
+    let mut it = fields.into_iter();
+    while let Some(status) = it.next() {
+        // renames and copies carry both the old and the new path
+        if status.starts_with('R') || status.starts_with('C') {
+            it.next();
+        }
+        let Some(path) = it.next() else { break };
+        if status.starts_with('D') {
+            changes.push(Change::Deleted(path));
+        } else {
+            changes.push(Change::Present(path));
+        }
+    }
+    Ok(changes)
+}
+
+/// Files in the tree of `rev` below `dir`, relative to it
+pub fn ls_tree(dir: &str, rev: &str) -> io::Result<Vec<String>> {
+    let out = git(dir, &["ls-tree", "-r", "--name-only", "-z", rev])?;
+    Ok(split_paths(&out))
+}
+
+/// Contents of `path` (relative to `dir`) as stored in `rev`
+pub fn show(dir: &str, rev: &str, path: &str) -> io::Result<String> {
+    let out = git(dir, &["show", &format!("{}:./{}", rev, path)])?;
+    String::from_utf8(out).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Files tracked in the index under `dir`, relative to it, optionally
+/// followed by untracked files that are not ignored
+pub fn ls_files(dir: &str, untracked: bool) -> io::Result<Vec<String>> {
+    let mut files = split_paths(&git(dir, &["ls-files", "-z"])?);
+    if untracked {
+        let out = git(dir, &["ls-files", "-z", "--others", "--exclude-standard"])?;
+        files.extend(split_paths(&out));
+    }
+    Ok(files)
+}
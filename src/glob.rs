@@ -0,0 +1,86 @@
+/// Matches `text` against a glob `pattern`. `*` and `?` stay within one path
+/// component, `**` also crosses `/`.
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    match_from(&pattern, &text)
+}
+
+/// Checks whether `pattern` contains any wildcard
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+fn match_from(p: &[char], t: &[char]) -> bool {
+    match p.first() {
+        None => t.is_empty(),
+        Some('*') if p.get(1) == Some(&'*') => {
+            let rest = &p[2..];
+            // `**/` may also stand for no directory at all
+            if rest.first() == Some(&'/') && match_from(&rest[1..], t) {
+                return true;
+            }
+            (0..=t.len()).any(|i| match_from(rest, &t[i..]))
+        }
+        Some('*') => {
+            let rest = &p[1..];
+            for i in 0..=t.len() {
+                if match_from(rest, &t[i..]) {
+                    return true;
+                }
+                if t.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => t.first().is_some_and(|c| *c != '/') && match_from(&p[1..], &t[1..]),
+        Some(c) => t.first() == Some(c) && match_from(&p[1..], &t[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_stays_in_one_component() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(matches("*", ""));
+        assert!(!matches("*.rs", "src/main.rs"));
+        assert!(matches("src/*.rs", "src/main.rs"));
+        assert!(!matches("src/*.rs", "src/bin/main.rs"));
+    }
+
+    #[test]
+    fn double_star_crosses_slashes() {
+        assert!(matches("**/*.rs", "main.rs"));
+        assert!(matches("**/*.rs", "src/bin/main.rs"));
+        assert!(matches("src/**/*.json", "src/a.json"));
+        assert!(matches("src/**/*.json", "src/x/y/a.json"));
+        assert!(!matches("src/**/*.json", "lib/a.json"));
+        assert!(matches("src/**", "src/x/y"));
+    }
+
+    #[test]
+    fn question_mark_is_one_character_but_not_a_slash() {
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(!matches("a?c", "abbc"));
+        assert!(!matches("a?c", "a/c"));
+    }
+
+    #[test]
+    fn plain_characters_match_exactly() {
+        assert!(matches("Makefile", "Makefile"));
+        assert!(!matches("Makefile", "makefile"));
+        assert!(!matches("src/a", "src/a/b"));
+    }
+
+    #[test]
+    fn detects_wildcards() {
+        assert!(is_glob("*.rs"));
+        assert!(is_glob("a?c"));
+        assert!(!is_glob("src/main.rs"));
+    }
+}
@@ -0,0 +1,40 @@
+use std::io::{self, BufRead};
+
+use regex::bytes::Regex;
+
+/// Content filters from `--grep` and `--exclude-grep`, matched line by line
+#[derive(Default)]
+pub struct Grep {
+    /// Keep only files with a line matching this
+    pub include: Option<Regex>,
+    /// Drop files with a line matching this
+    pub exclude: Option<Regex>,
+}
+
+impl Grep {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_none() && self.exclude.is_none()
+    }
+
+    /// Reads `contents` a line at a time, stopping as soon as the answer is
+    /// known, and tells whether the file passes both filters
+    pub fn keeps(&self, mut contents: impl BufRead) -> io::Result<bool> {
+        let mut included = self.include.is_none();
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if contents.read_until(b'\n', &mut line)? == 0 {
+                return Ok(included);
+            }
+            if self.exclude.as_ref().is_some_and(|re| re.is_match(&line)) {
+                return Ok(false);
+            }
+            if !included && self.include.as_ref().is_some_and(|re| re.is_match(&line)) {
+                if self.exclude.is_none() {
+                    return Ok(true);
+                }
+                included = true;
+            }
+        }
+    }
+}
@@ -1,8 +1,19 @@
+mod census;
 mod choice;
+mod config;
 mod dir;
+mod framework;
+mod frecency;
+mod git;
+mod glob;
+mod grep;
+mod pattern;
+mod picker;
 mod project_type;
 mod reatler;
 mod smart;
+mod tui;
+mod walk;
 fn main() {
     let args = std::env::args().collect::<Vec<String>>();
     reatler::run(&args);
@@ -0,0 +1,172 @@
+use std::{fmt, path::Path};
+
+use crate::glob;
+
+/// A rule deciding whether a file is included in the bundle
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pattern {
+    /// File extension without the dot, compared case-insensitively, e.g. `rs`.
+    /// Dotted ones such as `d.ts` are written `.d.ts` or `ext:d.ts`.
+    Extension(String),
+    /// Exact file name, e.g. `Makefile`
+    Name(String),
+    /// File name compared case-insensitively, e.g. `readme.md`
+    NameNoCase(String),
+    /// Wildcard over the file name, or over the trailing path components
+    /// when it contains a `/`, e.g. `*.config.js` or `src/**/*.json`
+    Glob(String),
+}
+
+impl Pattern {
+    /// Matches every file
+    pub fn any() -> Self {
+        Pattern::Glob("*".into())
+    }
+
+    /// Parses a pattern written in a project type table or configuration.
+    /// `.ext` is an extension, anything with `*` or `?` a glob and the rest an
+    /// exact file name. An explicit `ext:`, `name:`, `iname:` or `glob:` prefix
+    /// overrides the guess.
+    pub fn parse(s: &str) -> Self {
+        let s = s.trim();
+        if let Some(p) = Self::parse_prefixed(s) {
+            p
+        } else if glob::is_glob(s) {
+            Pattern::Glob(s.to_string())
+        } else if let Some(ext) = s.strip_prefix('.') {
+            Pattern::Extension(ext.to_string())
+        } else {
+            Pattern::Name(s.to_string())
+        }
+    }
+
+    /// Parses a pattern typed by the user, where a bare word such as `rs` means
+    /// an extension and a dotted word such as `package.json` a file name
+    pub fn from_user(s: &str) -> Self {
+        let s = s.trim();
+        if Self::parse_prefixed(s).is_none() && !glob::is_glob(s) && !s.contains('.') {
+            return Pattern::Extension(s.to_string());
+        }
+        Self::parse(s)
+    }
+
+    fn parse_prefixed(s: &str) -> Option<Self> {
+        let (kind, value) = s.split_once(':')?;
+        let value = value.to_string();
+        let p = match kind {
+            "ext" => Pattern::Extension(value.trim_start_matches('.').to_string()),
+            "name" => Pattern::Name(value),
+            "iname" => Pattern::NameNoCase(value),
+            "glob" => Pattern::Glob(value),
+            _ => return None,
+        };
+        Some(p)
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let path = Path::new(path);
+        let Some(name) = path.file_name().map(|n| n.to_string_lossy()) else {
+            return false;
+        };
+        match self {
+            Pattern::Extension(ext) => name
+                .to_lowercase()
+                .ends_with(&format!(".{}", ext.to_lowercase())),
+            Pattern::Name(n) => name == n.as_str(),
+            Pattern::NameNoCase(n) => name.to_lowercase() == n.to_lowercase(),
+            Pattern::Glob(g) if g.contains('/') => {
+                glob::matches(&format!("**/{}", g), &path.to_string_lossy())
+            }
+            Pattern::Glob(g) => glob::matches(g, &name),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Extension(ext) => write!(f, ".{}", ext),
+            Pattern::Name(n) => write!(f, "{}", n),
+            Pattern::NameNoCase(n) => write!(f, "iname:{}", n),
+            Pattern::Glob(g) => write!(f, "{}", g),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_guesses_the_kind() {
+        assert_eq!(Pattern::parse(".rs"), Pattern::Extension("rs".into()));
+        assert_eq!(Pattern::parse(".d.ts"), Pattern::Extension("d.ts".into()));
+        assert_eq!(Pattern::parse("Makefile"), Pattern::Name("Makefile".into()));
+        assert_eq!(
+            Pattern::parse("*.config.js"),
+            Pattern::Glob("*.config.js".into())
+        );
+        assert_eq!(Pattern::parse("src/?.rs"), Pattern::Glob("src/?.rs".into()));
+    }
+
+    #[test]
+    fn parse_honours_prefixes() {
+        assert_eq!(
+            Pattern::parse("ext:.d.ts"),
+            Pattern::Extension("d.ts".into())
+        );
+        assert_eq!(Pattern::parse("name:.env"), Pattern::Name(".env".into()));
+        assert_eq!(
+            Pattern::parse("iname:readme.md"),
+            Pattern::NameNoCase("readme.md".into())
+        );
+        assert_eq!(
+            Pattern::parse("glob:Makefile"),
+            Pattern::Glob("Makefile".into())
+        );
+    }
+
+    #[test]
+    fn from_user_reads_bare_words_as_extensions() {
+        assert_eq!(Pattern::from_user("rs"), Pattern::Extension("rs".into()));
+        assert_eq!(
+            Pattern::from_user(".d.ts"),
+            Pattern::Extension("d.ts".into())
+        );
+        assert_eq!(Pattern::from_user("d.ts"), Pattern::Name("d.ts".into()));
+        assert_eq!(
+            Pattern::from_user("package.json"),
+            Pattern::Name("package.json".into())
+        );
+        assert_eq!(
+            Pattern::from_user("name:Makefile"),
+            Pattern::Name("Makefile".into())
+        );
+    }
+
+    #[test]
+    fn extension_ignores_case() {
+        let p = Pattern::Extension("rs".into());
+        assert!(p.matches("src/Main.RS"));
+        assert!(!p.matches("src/main.rsx"));
+        assert!(Pattern::Extension("d.ts".into()).matches("types/index.d.ts"));
+        assert!(!Pattern::Extension("d.ts".into()).matches("types/index.ts"));
+    }
+
+    #[test]
+    fn glob_without_slash_sees_the_file_name() {
+        let p = Pattern::Glob("*.config.js".into());
+        assert!(p.matches("./web/vite.config.js"));
+        assert!(!p.matches("./web/config.js"));
+    }
+
+    #[test]
+    fn glob_with_slash_sees_trailing_components() {
+        let p = Pattern::Glob("src/**/*.json".into());
+        assert!(p.matches("./proj/src/a.json"));
+        assert!(p.matches("src/x/y/a.json"));
+        assert!(!p.matches("./proj/lib/a.json"));
+        assert!(Pattern::Glob("src/?.rs".into()).matches("./src/a.rs"));
+        assert!(!Pattern::Glob("src/?.rs".into()).matches("./src/ab.rs"));
+    }
+}
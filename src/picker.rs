@@ -0,0 +1,165 @@
+use std::io::{self, Stdout, Write};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{self, ClearType},
+};
+
+/// One line of the picker
+pub struct Item {
+    pub label: String,
+    pub ticked: bool,
+}
+
+/// A named group of items ticked together, e.g. a detected project type
+pub struct Preset {
+    pub name: String,
+    pub items: Vec<usize>,
+}
+
+/// Shows a full-screen checklist driven by the keyboard: arrows move, space
+/// toggles, `a` toggles everything, digits apply presets, Enter confirms and
+/// Esc cancels. Returns `false` when cancelled.
+pub fn pick(title: &str, items: &mut [Item], presets: &[Preset]) -> io::Result<bool> {
+    full_screen(|out| run(out, title, items, presets))
+}
+
+/// Runs `ui` on the alternate screen in raw mode, restoring the terminal
+/// afterwards even when `ui` fails
+pub fn full_screen<T>(ui: impl FnOnce(&mut Stdout) -> io::Result<T>) -> io::Result<T> {
+    let mut out = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = ui(&mut out);
+    execute!(out, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+/// Terminal columns and rows; some pseudo-terminals report no size at all,
+/// so zero falls back to 80×24
+pub fn screen_size() -> (usize, usize) {
+    let (cols, rows) = terminal::size().unwrap_or((0, 0));
+    (
+        if cols == 0 { 80 } else { cols as usize },
+        if rows == 0 { 24 } else { rows as usize },
+    )
+}
+
+/// What a key press means to a full-screen list
+pub enum Key {
+    /// The cursor moved to this row
+    Moved(usize),
+    /// Ctrl-C, Esc or `q`
+    Cancel,
+    Other(KeyEvent),
+}
+
+/// Waits for the next key press
+pub fn next_key() -> io::Result<KeyEvent> {
+    loop {
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                return Ok(key);
+            }
+        }
+    }
+}
+
+/// Applies the keys every list shares: arrows, `j`/`k` and PageUp/PageDown
+/// move `cursor` among `len` rows, `page` at a time for the latter
+pub fn navigate(key: KeyEvent, cursor: usize, len: usize, page: usize) -> Key {
+    let last = len.saturating_sub(1);
+    match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Key::Cancel,
+        KeyCode::Up | KeyCode::Char('k') => Key::Moved(cursor.saturating_sub(1)),
+        KeyCode::Down | KeyCode::Char('j') => Key::Moved((cursor + 1).min(last)),
+        KeyCode::PageUp => Key::Moved(cursor.saturating_sub(page)),
+        KeyCode::PageDown => Key::Moved((cursor + page).min(last)),
+        KeyCode::Esc | KeyCode::Char('q') => Key::Cancel,
+        _ => Key::Other(key),
+    }
+}
+
+fn run(
+    out: &mut impl Write,
+    title: &str,
+    items: &mut [Item],
+    presets: &[Preset],
+) -> io::Result<bool> {
+    let mut cursor_at = 0;
+    let mut offset = 0;
+    loop {
+        let (_, rows) = screen_size();
+        // title, help and preset lines take the top of the screen
+        let header = 3 + usize::from(!presets.is_empty());
+        let visible = rows.saturating_sub(header).max(1);
+        if cursor_at < offset {
+            offset = cursor_at;
+        } else if cursor_at >= offset + visible {
+            offset = cursor_at + 1 - visible;
+        }
+
+        queue!(
+            out,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0),
+            Print(title),
+            Print("\r\n"),
+            Print("↑/↓ move  space toggle  a all  Enter confirm  Esc cancel\r\n"),
+        )?;
+        if !presets.is_empty() {
+            let names: Vec<String> = presets
+                .iter()
+                .enumerate()
+                .map(|(i, p)| format!("{}) {}", i + 1, p.name))
+                .collect();
+            queue!(out, Print(format!("Presets: {}\r\n", names.join("  "))))?;
+        }
+        queue!(out, Print("\r\n"))?;
+        for (i, item) in items.iter().enumerate().skip(offset).take(visible) {
+            let marker = if i == cursor_at { '>' } else { ' ' };
+            let check = if item.ticked { 'x' } else { ' ' };
+            queue!(
+                out,
+                Print(format!("{} [{}] {}\r\n", marker, check, item.label))
+            )?;
+        }
+        out.flush()?;
+
+        let key = match navigate(next_key()?, cursor_at, items.len(), visible) {
+            Key::Moved(to) => {
+                cursor_at = to;
+                continue;
+            }
+            Key::Cancel => return Ok(false),
+            Key::Other(key) => key,
+        };
+        match key.code {
+            KeyCode::Char(' ') => {
+                if let Some(item) = items.get_mut(cursor_at) {
+                    item.ticked = !item.ticked;
+                }
+            }
+            KeyCode::Char('a') => {
+                let tick = items.iter().any(|i| !i.ticked);
+                items.iter_mut().for_each(|i| i.ticked = tick);
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() => {
+                let n = c.to_digit(10).unwrap_or(0) as usize;
+                if let Some(preset) = n.checked_sub(1).and_then(|i| presets.get(i)) {
+                    for &i in &preset.items {
+                        if let Some(item) = items.get_mut(i) {
+                            item.ticked = true;
+                        }
+                    }
+                }
+            }
+            KeyCode::Enter => return Ok(true),
+            _ => {}
+        }
+    }
+}
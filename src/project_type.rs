@@ -1,6 +1,106 @@
 use std::fmt;
 
-#[derive(Eq, PartialEq)]
+use crate::{config::Config, pattern::Pattern};
+
+/// A file or directory whose presence marks a project
+#[derive(Clone)]
+pub struct Marker {
+    pattern: Pattern,
+    dir: bool,
+}
+
+impl Marker {
+    /// Parses a marker written like an include pattern (`Cargo.toml`, `.csproj`,
+    /// `requirements*.txt`); a trailing `/` makes it match directories, e.g.
+    /// `.xcodeproj/`
+    pub fn parse(s: &str) -> Self {
+        let s = s.trim();
+        match s.strip_suffix('/') {
+            Some(d) => Marker {
+                pattern: Pattern::parse(d),
+                dir: true,
+            },
+            None => Marker {
+                pattern: Pattern::parse(s),
+                dir: false,
+            },
+        }
+    }
+
+    pub fn matches(&self, name: &str, is_dir: bool) -> bool {
+        self.dir == is_dir && self.pattern.matches(name)
+    }
+}
+
+/// A project type as used by detection: a built-in one, possibly adjusted by
+/// configuration, or one defined entirely in configuration
+#[derive(Clone)]
+pub struct ProjectDef {
+    pub name: String,
+    pub markers: Vec<Marker>,
+    pub include: Vec<Pattern>,
+    /// Directory names skipped whenever this type is detected
+    pub ignore: Vec<String>,
+    /// Language tag for the code fence wrapped around matching files
+    pub fence: Option<String>,
+}
+
+/// Built-in project types merged with the ones from `config`. A configured type
+/// named like a built-in one replaces the lists it sets, or appends to them
+/// with `extend = true`.
+pub fn registry(config: &Config) -> Vec<ProjectDef> {
+    let mut defs: Vec<ProjectDef> = ProjectType::ALL.iter().map(|t| t.def()).collect();
+    for tc in &config.types {
+        let markers = tc
+            .markers
+            .as_ref()
+            .map(|m| m.iter().map(|s| Marker::parse(s)).collect::<Vec<_>>());
+        let include = tc
+            .include
+            .as_ref()
+            .map(|i| i.iter().map(|s| Pattern::parse(s)).collect::<Vec<_>>());
+        let ignore = tc.ignore.clone();
+        match defs
+            .iter_mut()
+            .find(|d| d.name.eq_ignore_ascii_case(&tc.name))
+        {
+            Some(def) if tc.extend => {
+                def.markers.extend(markers.unwrap_or_default());
+                def.include.extend(include.unwrap_or_default());
+                def.ignore.extend(ignore.unwrap_or_default());
+            }
+            Some(def) => {
+                if let Some(markers) = markers {
+                    def.markers = markers;
+                }
+                if let Some(include) = include {
+                    def.include = include;
+                }
+                if let Some(ignore) = ignore {
+                    def.ignore = ignore;
+                }
+            }
+            None => defs.push(ProjectDef {
+                name: tc.name.clone(),
+                markers: markers.unwrap_or_default(),
+                include: include.unwrap_or_default(),
+                ignore: ignore.unwrap_or_default(),
+                fence: None,
+            }),
+        }
+        if let Some(def) = defs
+            .iter_mut()
+            .find(|d| d.name.eq_ignore_ascii_case(&tc.name))
+        {
+            if tc.fence.is_some() {
+                def.fence = tc.fence.clone();
+            }
+        }
+    }
+    defs
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
 pub enum ProjectType {
     Javascript,
     Rust,
@@ -53,118 +153,201 @@ impl fmt::Display for ProjectType {
     }
 }
 impl ProjectType {
-    pub fn from(file: &str) -> Vec<Self> {
-        let mut types = Vec::new();
+    pub const ALL: [Self; 21] = [
+        Self::Javascript,
+        Self::Rust,
+        Self::Go,
+        Self::C,
+        Self::Cpp,
+        Self::Python,
+        Self::Java,
+        Self::Kotlin,
+        Self::Swift,
+        Self::Php,
+        Self::Ruby,
+        Self::Shell,
+        Self::Dart,
+        Self::Haskell,
+        Self::Scala,
+        Self::Perl,
+        Self::R,
+        Self::Elixir,
+        Self::CSharp,
+        Self::FSharp,
+        Self::Lua,
+    ];
 
-        match file {
-            // JavaScript/TypeScript specific files
-            "package.json" | "yarn.lock" | "pnpm-lock.yaml" | "vite.config.js"
-            | "webpack.config.js" => {
-                types.push(Self::Javascript);
-            }
-            // Rust specific files
-            "Cargo.toml" | "Cargo.lock" | "build.rs" => {
-                types.push(Self::Rust);
-            }
-            // Go specific files
-            "go.mod" | "go.sum" => {
-                types.push(Self::Go);
-            }
-            // C-specific files
-            "Makefile" | "config.h" => {
-                types.push(Self::C);
-            }
-            // C++ specific files
-            "CMakeLists.txt" | ".clang-format" | ".clang-tidy" => {
-                types.push(Self::Cpp);
-                types.push(Self::C);
-            }
-            // Python specific files
-            "requirements.txt" | "Pipfile" | "pyproject.toml" | "setup.py" | "tox.ini" => {
-                types.push(Self::Python);
-            }
-            // Java specific files
-            "pom.xml" | "build.gradle" | "settings.gradle" => {
-                types.push(Self::Java);
-            }
-            // Kotlin specific files
-            "build.gradle.kts" | "settings.gradle.kts" => {
-                types.push(Self::Kotlin);
-            }
-            // Swift specific files
-            "Package.swift" | "Info.plist" | ".xcodeproj" | ".xcworkspace" => {
-                types.push(Self::Swift);
-            }
-            // PHP specific files
-            "composer.json" | "composer.lock" => {
-                types.push(Self::Php);
-            }
-            // Ruby specific files
-            "Gemfile" | "Gemfile.lock" | "Rakefile" => {
-                types.push(Self::Ruby);
-            }
-            // Shell specific files
-            ".sh" | ".bashrc" | ".zshrc" | ".profile" => {
-                types.push(Self::Shell);
-            }
-            // Dart specific files
-            "pubspec.yaml" | ".packages" => {
-                types.push(Self::Dart);
-            }
-            // Haskell specific files
-            "stack.yaml" | "cabal.project" | ".ghci" => {
-                types.push(Self::Haskell);
-            }
-            // Scala specific files
-            "build.sbt" => {
-                types.push(Self::Scala);
-            }
-            // Perl specific files
-            "Makefile.PL" => {
-                types.push(Self::Perl);
-            }
-            // R specific files
-            "DESCRIPTION" | "NAMESPACE" => {
-                types.push(Self::R);
-            }
-            // Elixir specific files
-            "mix.exs" => {
-                types.push(Self::Elixir);
-            }
-            // C# specific files
-            ".csproj" | ".sln" | "app.config" => {
-                types.push(Self::CSharp);
-            }
-            // F# specific files
-            ".fsproj" => {
-                types.push(Self::FSharp);
-            }
-            // Lua specific files
-            "init.lua" | ".luacheckrc" => {
-                types.push(Self::Lua);
-            }
-            _ => {}
+    /// Detection definition of this built-in type
+    pub fn def(&self) -> ProjectDef {
+        ProjectDef {
+            name: self.to_string(),
+            markers: self.markers(),
+            include: self.get_files(),
+            ignore: self
+                .default_ignores()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            fence: None,
+        }
+    }
+
+    /// Files and directories whose presence marks a project of this type
+    pub fn markers(&self) -> Vec<Marker> {
+        self.marker_patterns()
+            .into_iter()
+            .map(Marker::parse)
+            .collect()
+    }
+
+    fn marker_patterns(&self) -> Vec<&'static str> {
+        match self {
+            Self::Javascript => vec![
+                "package.json",
+                "yarn.lock",
+                "pnpm-lock.yaml",
+                "vite.config.js",
+                "webpack.config.js",
+            ],
+            Self::Rust => vec!["Cargo.toml", "Cargo.lock", "build.rs"],
+            Self::Go => vec!["go.mod", "go.sum"],
+            Self::C => vec![
+                "Makefile",
+                "config.h",
+                "CMakeLists.txt",
+                "name:.clang-format",
+                "name:.clang-tidy",
+            ],
+            Self::Cpp => vec!["CMakeLists.txt", "name:.clang-format", "name:.clang-tidy"],
+            Self::Python => vec![
+                "requirements*.txt",
+                "Pipfile",
+                "pyproject.toml",
+                "setup.py",
+                "tox.ini",
+            ],
+            Self::Java => vec!["pom.xml", "build.gradle", "settings.gradle"],
+            Self::Kotlin => vec!["build.gradle.kts", "settings.gradle.kts"],
+            Self::Swift => vec![
+                "Package.swift",
+                "Info.plist",
+                ".xcodeproj/",
+                ".xcworkspace/",
+            ],
+            Self::Php => vec!["composer.json", "composer.lock"],
+            Self::Ruby => vec!["Gemfile", "Gemfile.lock", "Rakefile", ".gemspec"],
+            Self::Shell => vec![".sh", "name:.bashrc", "name:.zshrc", "name:.profile"],
+            Self::Dart => vec!["pubspec.yaml", "name:.packages"],
+            Self::Haskell => vec!["stack.yaml", "cabal.project", "name:.ghci", ".cabal"],
+            Self::Scala => vec!["build.sbt"],
+            Self::Perl => vec!["Makefile.PL"],
+            Self::R => vec!["DESCRIPTION", "NAMESPACE"],
+            Self::Elixir => vec!["mix.exs"],
+            Self::CSharp => vec![".csproj", ".sln", "app.config"],
+            Self::FSharp => vec![".fsproj"],
+            Self::Lua => vec!["init.lua", "name:.luacheckrc"],
+        }
+    }
+
+    /// Build output and dependency directories never worth bundling
+    pub fn default_ignores(&self) -> Vec<&'static str> {
+        match self {
+            Self::Javascript => vec![
+                "node_modules",
+                "dist",
+                ".next",
+                ".nuxt",
+                ".svelte-kit",
+                ".turbo",
+                "coverage",
+            ],
+            Self::Rust => vec!["target"],
+            Self::Go => vec!["vendor"],
+            Self::C => vec!["build"],
+            Self::Cpp => vec!["build", "cmake-build-debug", "cmake-build-release"],
+            Self::Python => vec![
+                "__pycache__",
+                ".venv",
+                "venv",
+                ".tox",
+                ".mypy_cache",
+                ".pytest_cache",
+                "build",
+                "dist",
+            ],
+            Self::Java => vec!["target", "build", ".gradle"],
+            Self::Kotlin => vec!["build", ".gradle"],
+            Self::Swift => vec![".build", "Pods", "DerivedData"],
+            Self::Php => vec!["vendor"],
+            Self::Ruby => vec!["vendor", ".bundle"],
+            Self::Shell => vec![],
+            Self::Dart => vec![".dart_tool", "build"],
+            Self::Haskell => vec![".stack-work", "dist-newstyle"],
+            Self::Scala => vec!["target", ".bsp", ".metals"],
+            Self::Perl => vec!["blib"],
+            Self::R => vec!["renv"],
+            Self::Elixir => vec!["_build", "deps"],
+            Self::CSharp => vec!["bin", "obj"],
+            Self::FSharp => vec!["bin", "obj"],
+            Self::Lua => vec!["lua_modules"],
         }
+    }
+
+    /// Language a source file extension (without the dot) belongs to
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        let t = match ext.to_lowercase().as_str() {
+            "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" | "vue" | "svelte" => Self::Javascript,
+            "rs" => Self::Rust,
+            "go" => Self::Go,
+            "c" | "h" => Self::C,
+            "cpp" | "hpp" | "cc" | "cxx" | "hxx" => Self::Cpp,
+            "py" => Self::Python,
+            "java" => Self::Java,
+            "kt" | "kts" => Self::Kotlin,
+            "swift" => Self::Swift,
+            "php" => Self::Php,
+            "rb" => Self::Ruby,
+            "sh" | "bash" | "zsh" => Self::Shell,
+            "dart" => Self::Dart,
+            "hs" => Self::Haskell,
+            "scala" | "sc" => Self::Scala,
+            "pl" | "pm" => Self::Perl,
+            "r" => Self::R,
+            "ex" | "exs" => Self::Elixir,
+            "cs" => Self::CSharp,
+            "fs" | "fsx" => Self::FSharp,
+            "lua" => Self::Lua,
+            _ => return None,
+        };
+        Some(t)
+    }
 
-        types
+    /// Include patterns for files belonging to this type
+    pub fn get_files(&self) -> Vec<Pattern> {
+        self.file_patterns()
+            .into_iter()
+            .map(Pattern::parse)
+            .collect()
     }
 
-    pub fn get_files(&self) -> Vec<&str> {
+    fn file_patterns(&self) -> Vec<&'static str> {
         match self {
             Self::Javascript => vec![
                 ".ts",
                 ".js",
                 ".jsx",
                 ".tsx",
-                ".json",
                 "package.json",
+                "tsconfig.json",
+                "tsconfig.*.json",
+                "jsconfig.json",
                 "yarn.lock",
                 "pnpm-lock.yaml",
                 "vite.config.js",
                 "webpack.config.js",
-                ".eslintrc",
-                ".prettierrc",
-                ".babelrc",
+                ".eslintrc*",
+                ".prettierrc*",
+                ".babelrc*",
                 ".svelte",
                 ".vue",
                 ".nuxt",
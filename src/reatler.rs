@@ -1,18 +1,30 @@
 use arboard::{Clipboard, SetExtLinux};
+use regex::bytes::Regex;
 
 use crate::{
+    census,
     choice::{self, get_scan_type, ScanType},
-    dir::{scan_dir, ScanParams},
-    project_type::ProjectType,
+    config,
+    dir::{filter_files, scan_dir, ScanParams, Scope},
+    framework::Framework,
+    frecency::Frecency,
+    git,
+    grep::Grep,
+    pattern::Pattern,
+    picker::{self, Item},
+    project_type::{registry, ProjectDef},
+    walk::Symlinks,
 };
 use std::{
+    collections::{BTreeMap, HashSet},
     fs::{read_to_string, File},
-    io::{Read, Write},
+    io::{self, BufRead, BufReader, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
     process::exit,
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
-use crate::smart;
+use crate::{smart, tui};
 pub fn parse_gitignore() -> Vec<String> {
     let mut ignore = Vec::new();
     if let Ok(mut f) = File::open(".gitignore") {
@@ -26,21 +38,362 @@ pub fn parse_gitignore() -> Vec<String> {
     ignore
 }
 
-/// Returns (target_dir, optional_smart_query)
-fn parse_args(args: &[String]) -> (String, Option<String>) {
-    let mut dir = "./".to_string();
-    let mut smart_q = None;
+/// Options collected from the command line
+struct Args {
+    dir: String,
+    /// Whether `dir` was given on the command line rather than defaulted
+    explicit_dir: bool,
+    smart: Option<String>,
+    changed_since: Option<String>,
+    staged: bool,
+    /// Range passed to `git diff` when bundling a diff
+    diff: Option<String>,
+    rev: Option<String>,
+    tracked_only: bool,
+    include_untracked: bool,
+    /// File listing paths to bundle, `-` for stdin
+    files_from: Option<String>,
+    /// Skip the dependency and build directories of detected project types
+    default_ignores: bool,
+    /// Project types to keep when several are detected
+    types: Option<Vec<String>>,
+    /// Hand-pick files in the terminal browser
+    pick: bool,
+    /// Take the smart-mode match that clearly dominates without asking
+    auto_select: bool,
+    /// Smart-mode matches to take without asking, e.g. `1,3,5-7` or `all`
+    smart_pick: Option<String>,
+    /// Query for files to find by name instead of directories
+    smart_file: Option<String>,
+    /// What to bundle along with each file found by `--smart-file`
+    neighbours: Neighbours,
+    symlinks: Symlinks,
+    /// Limits copied into the final scan parameters
+    max_depth: Option<usize>,
+    include_hidden: bool,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    modified_since: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+    /// Content filters applied after scanning
+    grep: Grep,
+}
+
+/// Files bundled along with those found by `--smart-file`
+#[derive(Clone, Copy, PartialEq)]
+enum Neighbours {
+    None,
+    /// The other files in the same directory
+    Siblings,
+    /// Everything auto mode finds in the enclosing project
+    Module,
+}
+
+/// Where the bundled files are read from
+enum Source {
+    /// Files on disk
+    WorkTree,
+    /// Blobs stored in a git revision, looked up from the scanned directory
+    /// so it need not exist in the working tree
+    Rev { rev: String, dir: String },
+    /// Files on disk listed in the git index, plus untracked ones if asked
+    Tracked { untracked: bool },
+}
+
+impl Source {
+    /// Where the command line asks files under `dir` to be taken from
+    fn from_args(opts: &Args, dir: &str) -> Self {
+        match &opts.rev {
+            Some(rev) => Source::Rev {
+                rev: rev.clone(),
+                dir: dir.to_string(),
+            },
+            None if opts.tracked_only || opts.include_untracked => Source::Tracked {
+                untracked: opts.include_untracked,
+            },
+            None => Source::WorkTree,
+        }
+    }
+
+    /// Lists the files under `dir` matching `params`
+    fn scan(&self, dir: &str, params: ScanParams) -> io::Result<Vec<String>> {
+        match self {
+            Source::WorkTree => scan_dir(dir, params, true),
+            Source::Rev { rev, .. } => {
+                let files = git::ls_tree(dir, rev)?
+                    .into_iter()
+                    .map(|p| Path::new(dir).join(p).to_string_lossy().to_string())
+                    .collect();
+                Ok(filter_files(dir, files, &params))
+            }
+            Source::Tracked { untracked } => {
+                let mut files: Vec<String> = git::ls_files(dir, *untracked)?
+                    .into_iter()
+                    .map(|p| Path::new(dir).join(p).to_string_lossy().to_string())
+                    .filter(|p| Path::new(p).is_file())
+                    .collect();
+                files.sort();
+                // git already applies .gitignore, but patterns typed in manual
+                // mode still count
+                let gitignore = parse_gitignore();
+                let params = ScanParams {
+                    ignore: params
+                        .ignore
+                        .into_iter()
+                        .filter(|p| !gitignore.contains(p))
+                        .collect(),
+                    ..params
+                };
+                Ok(filter_files(dir, files, &params))
+            }
+        }
+    }
+
+    /// Opens a file returned by `scan` to be read a piece at a time
+    fn open(&self, file: &str) -> io::Result<Box<dyn BufRead>> {
+        match self {
+            Source::WorkTree | Source::Tracked { .. } => {
+                Ok(Box::new(BufReader::new(File::open(file)?)))
+            }
+            // git hands the blob over in one piece anyway
+            Source::Rev { .. } => Ok(Box::new(io::Cursor::new(self.read(file)?))),
+        }
+    }
+
+    /// Whether `file` exists, even where a scan would leave it out
+    fn exists(&self, file: &str) -> bool {
+        match self {
+            Source::WorkTree | Source::Tracked { .. } => Path::new(file).is_file(),
+            Source::Rev { .. } => self.read(file).is_ok(),
+        }
+    }
+
+    /// Reads a file returned by `scan`
+    fn read(&self, file: &str) -> io::Result<String> {
+        match self {
+            Source::WorkTree | Source::Tracked { .. } => read_to_string(file),
+            Source::Rev { rev, dir } => {
+                let rel = Path::new(file).strip_prefix(dir).unwrap_or(Path::new(file));
+                git::show(dir, rev, &rel.to_string_lossy())
+            }
+        }
+    }
+}
+
+/// Returns the value following the flag at `i`, or exits with an error
+fn flag_value(args: &[String], i: usize, msg: &str) -> String {
+    args.get(i + 1).cloned().unwrap_or_else(|| {
+        eprintln!("{}", msg);
+        exit(1)
+    })
+}
+
+/// Parses a byte count with an optional `k`, `m` or `g` suffix (powers of 1024)
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim().to_lowercase();
+    let value = value.strip_suffix('b').unwrap_or(&value);
+    let (number, unit) = match value.char_indices().last()? {
+        (i, 'k') => (&value[..i], 1 << 10),
+        (i, 'm') => (&value[..i], 1 << 20),
+        (i, 'g') => (&value[..i], 1 << 30),
+        _ => (value, 1),
+    };
+    number.trim().parse::<u64>().ok()?.checked_mul(unit)
+}
+
+/// Turns an age like `30m`, `2d` or `1w` into the moment that long ago
+fn parse_age(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+    let (i, unit) = value.char_indices().last()?;
+    let seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    let count: u64 = value[..i].parse().ok()?;
+    SystemTime::now().checked_sub(Duration::from_secs(count.checked_mul(seconds)?))
+}
+
+fn parse_args(args: &[String]) -> Args {
+    let mut parsed = Args {
+        dir: "./".to_string(),
+        explicit_dir: false,
+        smart: None,
+        changed_since: None,
+        staged: false,
+        diff: None,
+        rev: None,
+        tracked_only: false,
+        include_untracked: false,
+        files_from: None,
+        default_ignores: true,
+        types: None,
+        pick: false,
+        auto_select: false,
+        smart_pick: None,
+        smart_file: None,
+        neighbours: Neighbours::None,
+        symlinks: Symlinks::Inside,
+        max_depth: None,
+        include_hidden: true,
+        min_size: None,
+        max_size: None,
+        modified_since: None,
+        modified_before: None,
+        grep: Grep::default(),
+    };
     let mut i = 1;
     while i < args.len() {
         match args[i].as_str() {
             "--smart" => {
-                if let Some(q) = args.get(i + 1) {
-                    smart_q = Some(q.clone());
-                    i += 2;
+                parsed.smart = Some(flag_value(args, i, "--smart requires a query"));
+                i += 2;
+            }
+            "--changed-since" => {
+                parsed.changed_since =
+                    Some(flag_value(args, i, "--changed-since requires a git ref"));
+                i += 2;
+            }
+            "--staged" => {
+                parsed.staged = true;
+                i += 1;
+            }
+            "--diff" => {
+                parsed.diff.get_or_insert_with(|| "HEAD".to_string());
+                i += 1;
+            }
+            "--rev" => {
+                parsed.rev = Some(flag_value(args, i, "--rev requires a git revision"));
+                i += 2;
+            }
+            "--tracked-only" => {
+                parsed.tracked_only = true;
+                i += 1;
+            }
+            "--include-untracked" => {
+                parsed.include_untracked = true;
+                i += 1;
+            }
+            "--files-from" => {
+                parsed.files_from = Some(flag_value(args, i, "--files-from requires a path or -"));
+                i += 2;
+            }
+            "--no-default-ignores" => {
+                parsed.default_ignores = false;
+                i += 1;
+            }
+            "--pick" => {
+                parsed.pick = true;
+                i += 1;
+            }
+            "--select" => {
+                parsed.smart_pick = Some(flag_value(
+                    args,
+                    i,
+                    "--select requires a selection, e.g. 1,3,5-7 or all",
+                ));
+                i += 2;
+            }
+            "--smart-file" => {
+                parsed.smart_file = Some(flag_value(args, i, "Missing query after --smart-file"));
+                i += 2;
+            }
+            "--siblings" => {
+                parsed.neighbours = Neighbours::Siblings;
+                i += 1;
+            }
+            "--module" => {
+                parsed.neighbours = Neighbours::Module;
+                i += 1;
+            }
+            "--follow-symlinks" => {
+                parsed.symlinks = Symlinks::Follow;
+                i += 1;
+            }
+            "--no-follow-symlinks" => {
+                parsed.symlinks = Symlinks::Never;
+                i += 1;
+            }
+            "--max-depth" => {
+                let depth = flag_value(args, i, "--max-depth requires a number");
+                // depth 1 is the directory's own files, so 0 would bundle nothing
+                match depth.parse() {
+                    Ok(n) if n > 0 => parsed.max_depth = Some(n),
+                    _ => {
+                        eprintln!("--max-depth requires a number of at least 1, got {}", depth);
+                        exit(1)
+                    }
+                }
+                i += 2;
+            }
+            "--no-hidden" => {
+                parsed.include_hidden = false;
+                i += 1;
+            }
+            flag @ ("--min-size" | "--max-size") => {
+                let value = flag_value(args, i, &format!("{} requires a size, e.g. 10k", flag));
+                let size = parse_size(&value).unwrap_or_else(|| {
+                    eprintln!("Invalid size for {}: {}", flag, value);
+                    exit(1)
+                });
+                if flag == "--min-size" {
+                    parsed.min_size = Some(size);
+                } else {
+                    parsed.max_size = Some(size);
+                }
+                i += 2;
+            }
+            flag @ ("--since" | "--before") => {
+                let value = flag_value(args, i, &format!("{} requires an age, e.g. 2d", flag));
+                let time = parse_age(&value).unwrap_or_else(|| {
+                    eprintln!("Invalid age for {}: {}", flag, value);
+                    exit(1)
+                });
+                if flag == "--since" {
+                    parsed.modified_since = Some(time);
                 } else {
-                    eprintln!("--smart requires a query");
-                    exit(1);
+                    parsed.modified_before = Some(time);
                 }
+                i += 2;
+            }
+            flag @ ("--grep" | "--exclude-grep") => {
+                let value = flag_value(args, i, &format!("{} requires a regex", flag));
+                let re = Regex::new(&value).unwrap_or_else(|e| {
+                    eprintln!("Invalid regex for {}: {}", flag, e);
+                    exit(1)
+                });
+                if flag == "--grep" {
+                    parsed.grep.include = Some(re);
+                } else {
+                    parsed.grep.exclude = Some(re);
+                }
+                i += 2;
+            }
+            "--first" => {
+                parsed.smart_pick = Some("1".into());
+                i += 1;
+            }
+            "--auto-select" => {
+                parsed.auto_select = true;
+                i += 1;
+            }
+            "--types" => {
+                let list = flag_value(args, i, "--types requires a comma-separated list");
+                parsed.types = Some(
+                    list.split(',')
+                        .map(|t| t.trim().to_string())
+                        .filter(|t| !t.is_empty())
+                        .collect(),
+                );
+                i += 2;
+            }
+            "--diff-base" => {
+                let base = flag_value(args, i, "--diff-base requires a git ref");
+                parsed.diff = Some(format!("{}..HEAD", base));
+                i += 2;
             }
             flag if flag == "--manual" || flag == "-m" => {
                 // leave it to get_scan_type()
@@ -51,70 +404,544 @@ fn parse_args(args: &[String]) -> (String, Option<String>) {
                 i += 1;
             }
             path => {
-                dir = path.to_string();
+                parsed.dir = path.to_string();
+                parsed.explicit_dir = true;
                 i += 1;
             }
         }
     }
-    (dir, smart_q)
+    parsed
 }
 
 pub fn run(args: &[String]) {
     // 1) parse args
-    let (mut dir, smart_q) = parse_args(args);
+    let opts = parse_args(args);
+    if opts.smart_pick.is_some() && opts.smart.is_none() && opts.smart_file.is_none() {
+        eprintln!("--select and --first only apply to --smart or --smart-file");
+        exit(1)
+    }
+    // the diff itself covers every touched file, so it can't be filtered
+    if opts.diff.is_some() && !opts.grep.is_empty() {
+        eprintln!("--grep and --exclude-grep can't be combined with --diff or --diff-base");
+        exit(1)
+    }
+
+    // an explicit file list skips scanning and detection altogether
+    if let Some(list) = &opts.files_from {
+        let files = read_file_list(list).unwrap_or_else(|e| {
+            eprintln!("Error reading file list {}: {}", list, e);
+            exit(1)
+        });
+        let files = grep_files(files, &Source::WorkTree, &opts.grep);
+        write_bundle(&[(Source::WorkTree, files)], None, &[]);
+        return;
+    }
+
+    if let Some(query) = &opts.smart_file {
+        let (files, defs) = smart_file_lookup(&opts, query, args);
+        let files = grep_files(files, &Source::WorkTree, &opts.grep);
+        write_bundle(&[(Source::WorkTree, files)], None, &defs);
+        return;
+    }
 
     // 2) maybe do smart lookup
-    if let Some(query) = smart_q {
-        let gitignore = parse_gitignore();
-        let matches = match smart::find_directories(&dir, &query, &gitignore) {
-            Ok(v) if !v.is_empty() => v,
-            _ => {
-                eprintln!("No directories matching “{}” found under {}", query, dir);
+    let dirs = match &opts.smart {
+        Some(query) => smart_lookup(&opts, query),
+        None => vec![opts.dir.clone()],
+    };
+
+    // diff mode takes its file set straight from git
+    if let Some(range) = &opts.diff {
+        let mut files = Vec::new();
+        let mut prelude = String::new();
+        for dir in &dirs {
+            let (f, p) = diff_bundle(dir, range).unwrap_or_else(|e| {
+                eprintln!("Error reading git diff: {}", e);
                 exit(1)
+            });
+            files.extend(f);
+            prelude.push_str(&p);
+        }
+        write_bundle(&[(Source::WorkTree, files)], Some(&prelude), &[]);
+        return;
+    }
+
+    let mut parts = Vec::new();
+    let mut detected = Vec::new();
+    for dir in &dirs {
+        let source = Source::from_args(&opts, dir);
+        let (f, d) = collect_files(dir, &source, &opts, args);
+        let f = grep_files(f, &source, &opts.grep);
+        parts.push((source, f));
+        detected.extend(d);
+    }
+
+    // 5) output
+    write_bundle(&parts, None, &detected);
+}
+
+/// Keeps the files whose contents pass `--grep` and `--exclude-grep`
+fn grep_files(files: Vec<String>, source: &Source, grep: &Grep) -> Vec<String> {
+    if grep.is_empty() {
+        return files;
+    }
+    files
+        .into_iter()
+        .filter(|f| match source.open(f).and_then(|r| grep.keeps(r)) {
+            Ok(keep) => keep,
+            Err(e) => {
+                eprintln!("Skipping {}: {}", f, e);
+                false
             }
-        };
-        let choice = smart::choose_directory(&matches).unwrap_or_else(|| {
-            eprintln!("Invalid selection, aborting.");
+        })
+        .collect()
+}
+
+/// Whether the start of `file` reads as UTF-8 text without NUL bytes
+fn looks_like_text(file: &str) -> bool {
+    let mut head = Vec::new();
+    match File::open(file).and_then(|f| f.take(8192).read_to_end(&mut head)) {
+        // a character cut off by the 8 KiB limit is fine
+        Ok(_) => {
+            !head.contains(&0)
+                && std::str::from_utf8(&head)
+                    .err()
+                    .is_none_or(|e| e.error_len().is_none())
+        }
+        Err(_) => false,
+    }
+}
+
+/// Finds the directories matching `query` and lets the user, or `--select`,
+/// `--first` and `--auto-select`, choose among them
+fn smart_lookup(opts: &Args, query: &str) -> Vec<String> {
+    let roots = search_roots(opts);
+    let mut frecency = Frecency::load();
+    let matches = match smart::find_directories(&roots, query, &frecency) {
+        Ok(v) if !v.is_empty() => v,
+        _ => {
+            eprintln!(
+                "No directories matching “{}” found under {}",
+                query,
+                root_names(&roots)
+            );
             exit(1)
+        }
+    };
+    let chosen = match (&opts.smart_pick, smart::dominant(&matches)) {
+        (Some(spec), _) => smart::parse_selection(spec, matches.len()).map(|picked| {
+            picked
+                .into_iter()
+                .map(|i| matches[i].path.clone())
+                .collect()
+        }),
+        (None, Some(m)) if opts.auto_select => Some(vec![m.path.clone()]),
+        (None, _) => smart::choose_matches("directories", &matches, &|d| summarize(d, opts)),
+    };
+    let mut chosen: Vec<String> = chosen.unwrap_or_else(|| {
+        eprintln!("Invalid selection, aborting.");
+        exit(1)
+    });
+    for dir in &chosen {
+        frecency.record(dir);
+    }
+    // a directory inside another chosen one is already bundled with it
+    let all = chosen.clone();
+    chosen.retain(|d| {
+        !all.iter()
+            .any(|outer| outer != d && Path::new(d).starts_with(outer))
+    });
+    for dir in &chosen {
+        println!("\n→ Assembling files under: {}", dir);
+    }
+    println!();
+    if let Err(e) = frecency.save() {
+        eprintln!("Could not save smart-mode history: {}", e);
+    }
+    chosen
+}
+
+/// Where smart mode searches: the directory given on the command line, else
+/// the roots from the configuration, else the current directory
+fn search_roots(opts: &Args) -> Vec<smart::Root> {
+    let gitignore = parse_gitignore();
+    let here = || smart::Root {
+        path: opts.dir.clone(),
+        ignore: gitignore.clone(),
+        max_depth: None,
+        symlinks: opts.symlinks,
+    };
+    if opts.explicit_dir {
+        return vec![here()];
+    }
+    let config = config::load(&opts.dir).unwrap_or_else(|e| {
+        eprintln!("Error reading config: {}", e);
+        exit(1)
+    });
+    let roots: Vec<smart::Root> = config
+        .roots
+        .iter()
+        .map(|r| smart::Root {
+            path: r.expanded_path().to_string_lossy().to_string(),
+            ignore: gitignore.iter().chain(&r.ignore).cloned().collect(),
+            max_depth: r.max_depth,
+            symlinks: opts.symlinks,
+        })
+        .collect();
+    if roots.is_empty() {
+        vec![here()]
+    } else {
+        roots
+    }
+}
+
+fn root_names(roots: &[smart::Root]) -> String {
+    roots
+        .iter()
+        .map(|r| r.path.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Finds the files named like `query`, lets the user choose among them like
+/// `smart_lookup` does, and adds their siblings or modules if asked. Returns
+/// the files to bundle and the project types that apply to them.
+fn smart_file_lookup(opts: &Args, query: &str, args: &[String]) -> (Vec<String>, Vec<ProjectDef>) {
+    let gitignore = parse_gitignore();
+    let roots = search_roots(opts);
+    let matches = match smart::find_files(&roots, query) {
+        Ok(v) if !v.is_empty() => v,
+        _ => {
+            eprintln!(
+                "No files matching “{}” found under {}",
+                query,
+                root_names(&roots)
+            );
+            exit(1)
+        }
+    };
+    let describe = |f: &str| {
+        std::fs::metadata(f)
+            .map(|m| census::format_size(m.len()))
+            .unwrap_or_default()
+    };
+    let chosen = match &opts.smart_pick {
+        Some(spec) => smart::parse_selection(spec, matches.len()).map(|picked| {
+            picked
+                .into_iter()
+                .map(|i| matches[i].path.clone())
+                .collect()
+        }),
+        None => smart::choose_matches("files", &matches, &describe),
+    };
+    let chosen: Vec<String> = chosen.unwrap_or_else(|| {
+        eprintln!("Invalid selection, aborting.");
+        exit(1)
+    });
+
+    let config = config::load(&opts.dir).unwrap_or_else(|e| {
+        eprintln!("Error reading config: {}", e);
+        exit(1)
+    });
+    let mut defs = registry(&config);
+    let mut files = Vec::new();
+    let add = |f: String, files: &mut Vec<String>| {
+        if !files.contains(&f) {
+            files.push(f);
+        }
+    };
+    for file in chosen {
+        let parent = match Path::new(&file).parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_string_lossy().to_string(),
+            _ => ".".to_string(),
+        };
+        match opts.neighbours {
+            Neighbours::None => add(file, &mut files),
+            Neighbours::Siblings => {
+                add(file, &mut files);
+                let params = ScanParams {
+                    include: vec![Pattern::any()],
+                    ignore: gitignore.clone(),
+                    symlinks: opts.symlinks,
+                    ..Default::default()
+                };
+                // images and other binaries beside the file are no use in a bundle
+                for f in scan_dir(&parent, params, false).unwrap_or_default() {
+                    if looks_like_text(&f) {
+                        add(f, &mut files);
+                    }
+                }
+            }
+            Neighbours::Module => {
+                let module = module_root(Path::new(&parent), &roots, &defs);
+                println!("\n→ Assembling module: {}\n", module.display());
+                add(file, &mut files);
+                let (found, detected) =
+                    collect_files(&module.to_string_lossy(), &Source::WorkTree, opts, args);
+                for f in found {
+                    add(f, &mut files);
+                }
+                defs = detected.into_iter().chain(defs).collect();
+            }
+        }
+    }
+    (files, defs)
+}
+
+/// Closest directory from `dir` up to its search root holding a project
+/// marker, `dir` itself when there is none
+fn module_root(dir: &Path, roots: &[smart::Root], defs: &[ProjectDef]) -> PathBuf {
+    for ancestor in dir.ancestors() {
+        let has_marker = std::fs::read_dir(ancestor).is_ok_and(|entries| {
+            entries.filter_map(Result::ok).any(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                let is_dir = e.path().is_dir();
+                defs.iter()
+                    .any(|d| d.markers.iter().any(|m| m.matches(&name, is_dir)))
+            })
         });
-        println!("\n→ Assembling files under: {}\n", choice);
-        dir = choice;
+        if has_marker {
+            return ancestor.to_path_buf();
+        }
+        if roots.iter().any(|r| ancestor == Path::new(&r.path)) {
+            break;
+        }
     }
+    dir.to_path_buf()
+}
+
+/// Runs detection and scanning for one target directory. Returns the files to
+/// bundle and the project types detected there.
+fn collect_files(
+    dir: &str,
+    source: &Source,
+    opts: &Args,
+    args: &[String],
+) -> (Vec<String>, Vec<ProjectDef>) {
+    let config = config::load(dir).unwrap_or_else(|e| {
+        eprintln!("Error reading config: {}", e);
+        exit(1)
+    });
+    let defs = registry(&config);
 
     // 3) manual vs auto
     let is_manual = matches!(get_scan_type(args), ScanType::Manual);
-    let params = if is_manual {
-        get_scan_params_manual()
+    let (mut params, detected) = if is_manual {
+        (get_scan_params_manual(dir, source, &defs, opts), Vec::new())
     } else {
-        get_scan_params_auto(&dir).unwrap_or_else(|| {
+        get_scan_params_auto(dir, source, &defs, opts, false).unwrap_or_else(|| {
             println!("Auto-detection failed, falling back to manual.");
-            get_scan_params_manual()
+            (get_scan_params_manual(dir, source, &defs, opts), Vec::new())
         })
     };
+    apply_limits(&mut params, opts);
 
     // 4) scan
-    let files = match scan_dir(&dir, params, true) {
+    // the browser lists everything, but git's internals are never wanted
+    let mut browse_ignore = params.ignore.clone();
+    if !browse_ignore.iter().any(|p| p == ".git") {
+        browse_ignore.push(".git".into());
+    }
+    let browse_params = ScanParams {
+        include: vec![Pattern::any()],
+        ignore: browse_ignore,
+        skip_dirs: params.skip_dirs.clone(),
+        symlinks: opts.symlinks,
+        ..Default::default()
+    };
+    let mut files = match source.scan(dir, params) {
         Ok(v) => v,
         Err(e) => {
             eprintln!("Error scanning files: {}", e);
             exit(1)
         }
     };
-    for f in &files {
-        println!("+{}", f);
+    if let Some(changed) = changed_files(dir, opts).unwrap_or_else(|e| {
+        eprintln!("Error reading git changes: {}", e);
+        exit(1)
+    }) {
+        files.retain(|f| {
+            Path::new(f)
+                .strip_prefix(dir)
+                .is_ok_and(|rel| changed.contains(rel))
+        });
     }
+    if opts.pick {
+        let all = source.scan(dir, browse_params).unwrap_or_default();
+        let read = |f: &str| source.read(f).unwrap_or_default();
+        files = match tui::browse(dir, &all, &files, &read) {
+            Ok(Some(picked)) => picked,
+            Ok(None) => {
+                println!("Nothing picked, exiting.");
+                exit(0)
+            }
+            Err(e) => {
+                eprintln!("Error running the file browser: {}", e);
+                exit(1)
+            }
+        };
+    }
+    (files, detected)
+}
 
-    // 5) output
-    if let Err(e) = add_files(&files) {
+/// Copies the command-line scan limits into `params`
+fn apply_limits(params: &mut ScanParams, opts: &Args) {
+    params.symlinks = opts.symlinks;
+    params.max_depth = opts.max_depth;
+    params.include_hidden = opts.include_hidden;
+    params.min_size = opts.min_size;
+    params.max_size = opts.max_size;
+    params.modified_since = opts.modified_since;
+    params.modified_before = opts.modified_before;
+}
+
+/// One-line description of a smart-mode match: detected types, how many
+/// files auto mode would bundle and their size, and the git status
+fn summarize(dir: &str, opts: &Args) -> String {
+    let defs = registry(&config::load(dir).unwrap_or_default());
+    let source = Source::from_args(opts, dir);
+    let mut parts = Vec::new();
+    let bundled = match get_scan_params_auto(dir, &source, &defs, opts, true) {
+        Some((mut params, detected)) => {
+            let mut names: Vec<&str> = Vec::new();
+            for t in &detected {
+                if !names.contains(&t.name.as_str()) {
+                    names.push(&t.name);
+                }
+            }
+            parts.push(names.join(", "));
+            apply_limits(&mut params, opts);
+            source.scan(dir, params).unwrap_or_default()
+        }
+        None => {
+            parts.push("unknown type".to_string());
+            Vec::new()
+        }
+    };
+    let size: u64 = bundled
+        .iter()
+        .filter_map(|f| std::fs::metadata(f).ok())
+        .map(|m| m.len())
+        .sum();
+    parts.push(format!("{} files", bundled.len()));
+    parts.push(census::format_size(size));
+    match git::is_dirty(dir) {
+        Ok(true) => parts.push("dirty".into()),
+        Ok(false) => parts.push("clean".into()),
+        Err(_) => {}
+    }
+    parts.join(" · ")
+}
+
+/// Writes the files of each source, after `prelude`, to output.txt and
+/// copies it to the clipboard
+fn write_bundle(parts: &[(Source, Vec<String>)], prelude: Option<&str>, defs: &[ProjectDef]) {
+    if let Err(e) = add_files(parts, prelude, defs) {
         eprintln!("Error writing output: {}", e);
         exit(1)
     }
 }
 
-fn add_files(files: &[String]) -> Result<(), std::io::Error> {
+/// Reads newline- or NUL-separated paths from `list`, or stdin for `-`
+fn read_file_list(list: &str) -> io::Result<Vec<String>> {
+    let mut buf = String::new();
+    if list == "-" {
+        io::stdin().read_to_string(&mut buf)?;
+    } else {
+        File::open(list)?.read_to_string(&mut buf)?;
+    }
+    let sep = if buf.contains('\0') { '\0' } else { '\n' };
+    Ok(buf
+        .split(sep)
+        .map(|l| l.trim_end_matches('\r'))
+        .filter(|l| !l.trim().is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Builds the diff header and the list of touched files still present on
+/// disk for `range`
+fn diff_bundle(dir: &str, range: &str) -> std::io::Result<(Vec<String>, String)> {
+    let mut prelude = format!("\n Git diff: {}\n\n{}", range, git::diff(dir, range)?);
+    let mut files = Vec::new();
+    let mut deleted = Vec::new();
+    for change in git::touched(dir, range)? {
+        match change {
+            git::Change::Present(p) => {
+                let path = Path::new(dir).join(p);
+                // files changed in a range may have been removed since
+                if path.is_file() {
+                    files.push(path.to_string_lossy().to_string());
+                } else {
+                    deleted.push(path.to_string_lossy().to_string());
+                }
+            }
+            git::Change::Deleted(p) => {
+                deleted.push(Path::new(dir).join(p).to_string_lossy().to_string())
+            }
+        }
+    }
+    if !deleted.is_empty() {
+        prelude.push_str("\n Deleted files:\n\n");
+        for d in &deleted {
+            prelude.push_str(&format!("{}\n", d));
+        }
+    }
+    Ok((files, prelude))
+}
+
+/// Collects the files touched according to `--changed-since` and `--staged`,
+/// relative to `dir`. Returns `None` when neither flag is set.
+fn changed_files(dir: &str, opts: &Args) -> std::io::Result<Option<HashSet<PathBuf>>> {
+    if opts.changed_since.is_none() && !opts.staged {
+        return Ok(None);
+    }
+    let mut changed = HashSet::new();
+    if let Some(base) = &opts.changed_since {
+        changed.extend(
+            git::changed_since(dir, base)?
+                .into_iter()
+                .map(PathBuf::from),
+        );
+    }
+    if opts.staged {
+        changed.extend(git::staged(dir)?.into_iter().map(PathBuf::from));
+    }
+    Ok(Some(changed))
+}
+
+fn add_files(
+    parts: &[(Source, Vec<String>)],
+    prelude: Option<&str>,
+    defs: &[ProjectDef],
+) -> Result<(), std::io::Error> {
     let mut out = File::create("output.txt")?;
-    for f in files {
-        append_file_to_output(f, &mut out)?;
+    if let Some(prelude) = prelude {
+        out.write_all(prelude.as_bytes())?;
+    }
+    for (source, f) in parts
+        .iter()
+        .flat_map(|(source, files)| files.iter().map(move |f| (source, f)))
+    {
+        // the first detected type claiming the file decides its code fence
+        let fence = defs
+            .iter()
+            .filter(|d| d.include.iter().any(|p| p.matches(f)))
+            .find_map(|d| d.fence.as_deref());
+        // one binary or vanished file should not cost the whole bundle
+        let contents = match source.read(f) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                eprintln!("Skipping {}: not a text file", f);
+                continue;
+            }
+            Err(e) => {
+                eprintln!("Skipping {}: {}", f, e);
+                continue;
+            }
+        };
+        println!("+{}", f);
+        append_file_to_output(f, &contents, fence, &mut out)?;
     }
     if let Ok(mut clip) = Clipboard::new() {
         println!("Copied to clipboard\nPress ctrl+c when finished pasting");
@@ -128,59 +955,289 @@ fn add_files(files: &[String]) -> Result<(), std::io::Error> {
     Ok(())
 }
 
-fn append_file_to_output(file_name: &str, output_file: &mut File) -> Result<(), std::io::Error> {
-    let mut buf = String::new();
-    let mut src = File::open(file_name)?;
-    src.read_to_string(&mut buf)?;
+fn append_file_to_output(
+    file_name: &str,
+    contents: &str,
+    fence: Option<&str>,
+    output_file: &mut File,
+) -> Result<(), std::io::Error> {
     writeln!(output_file, "\n File path: {}\n", file_name)?;
-    output_file.write_all(buf.as_bytes())?;
+    if let Some(lang) = fence {
+        writeln!(output_file, "```{}", lang)?;
+    }
+    output_file.write_all(contents.as_bytes())?;
+    if fence.is_some() {
+        if !contents.ends_with('\n') {
+            writeln!(output_file)?;
+        }
+        writeln!(output_file, "```")?;
+    }
     Ok(())
 }
 
-fn get_scan_params_manual() -> ScanParams {
+fn get_scan_params_manual(
+    dir: &str,
+    source: &Source,
+    defs: &[ProjectDef],
+    opts: &Args,
+) -> ScanParams {
     let mut ignore = choice::get_ignore();
     ignore.extend(parse_gitignore());
+    let include = if io::stdin().is_terminal() {
+        let dry = ScanParams {
+            include: vec![Pattern::any()],
+            ignore: ignore.clone(),
+            symlinks: opts.symlinks,
+            ..Default::default()
+        };
+        let files = source.scan(dir, dry).unwrap_or_default();
+        let presets: Vec<(String, Vec<Pattern>)> = get_project_types(dir, &files, defs, false)
+            .unwrap_or_default()
+            .into_values()
+            .flatten()
+            .fold(Vec::new(), |mut presets, t| {
+                if !presets.iter().any(|(name, _)| *name == t.name) {
+                    presets.push((t.name, t.include));
+                }
+                presets
+            });
+        choice::pick_types(&files, &presets)
+    } else {
+        choice::get_types()
+    };
     ScanParams {
-        include: choice::get_types(),
+        include,
         ignore,
+        ..Default::default()
     }
 }
 
-fn get_scan_params_auto(dir: &str) -> Option<ScanParams> {
+/// Detects project types and frameworks in `dir` and builds the scan
+/// parameters for them. With `quiet` nothing is printed or asked: every
+/// detected type is kept unless `--types` says otherwise.
+fn get_scan_params_auto(
+    dir: &str,
+    source: &Source,
+    defs: &[ProjectDef],
+    opts: &Args,
+    quiet: bool,
+) -> Option<(ScanParams, Vec<ProjectDef>)> {
+    let default_ignores = opts.default_ignores;
     let gitignore = parse_gitignore();
+    // nothing is known yet, so keep out of every type's dependency directories
+    let mut skip_dirs: Vec<String> = Vec::new();
+    if default_ignores {
+        for d in defs.iter().flat_map(|d| &d.ignore) {
+            if !skip_dirs.contains(d) {
+                skip_dirs.push(d.clone());
+            }
+        }
+    }
     let dry = ScanParams {
-        include: vec!["".into()],
+        include: vec![Pattern::any()],
         ignore: gitignore.clone(),
+        skip_dirs,
+        symlinks: opts.symlinks,
+        ..Default::default()
     };
-    let files = scan_dir(dir, dry, false).unwrap_or_default();
-    let mut types = get_project_types(&files)?;
-    types.dedup();
+    let files = source.scan(dir, dry).unwrap_or_default();
+    let projects = get_project_types(dir, &files, defs, quiet).or_else(|| {
+        let types: Vec<ProjectDef> = census::detect(&files, quiet)?
+            .into_iter()
+            .filter_map(|t| defs.iter().find(|d| d.name == t.to_string()).cloned())
+            .collect();
+        if types.is_empty() {
+            return None;
+        }
+        Some(BTreeMap::from([(PathBuf::from(dir), types)]))
+    })?;
+    let projects = select_types(dir, &files, projects, opts.types.as_deref(), quiet)?;
     let mut params = ScanParams::default();
     params.ignore.extend(gitignore);
-    params
-        .include
-        .extend(types.iter().flat_map(|t| t.get_files()).map(String::from));
-    Some(params)
+    let mut detected = Vec::new();
+    let mut frameworks_found = Vec::new();
+    for (root, types) in projects {
+        let mut include: Vec<Pattern> = types.iter().flat_map(|t| t.include.clone()).collect();
+        let frameworks = Framework::detect(
+            // markers such as bin/rails may sit in directories the dry scan
+            // skipped, so ask the source itself
+            |f| source.exists(&root.join(f).to_string_lossy()),
+            |f| {
+                source
+                    .read(&root.join(f).to_string_lossy())
+                    .unwrap_or_default()
+            },
+        );
+        for fw in frameworks {
+            include.extend(fw.get_files());
+            if root == Path::new(dir) {
+                frameworks_found.push(fw.to_string());
+            } else {
+                frameworks_found.push(format!("{} ({})", fw, root.display()));
+            }
+        }
+        if default_ignores {
+            params
+                .skip_dirs
+                .extend(types.iter().flat_map(|t| t.ignore.clone()));
+        }
+        // markers at the top apply to the whole tree, nested ones to their subtree
+        if root == Path::new(dir) {
+            params.include = include;
+        } else {
+            params.scopes.push(Scope { root, include });
+        }
+        detected.extend(types);
+    }
+    if !quiet && !frameworks_found.is_empty() {
+        println!("Detected framework(s): {}", frameworks_found.join(", "));
+    }
+    Some((params, detected))
+}
+
+/// Narrows the detected types down to those named with `--types`, or, when
+/// several were found, to those ticked in an interactive checklist
+fn select_types(
+    dir: &str,
+    files: &[String],
+    projects: BTreeMap<PathBuf, Vec<ProjectDef>>,
+    only: Option<&[String]>,
+    quiet: bool,
+) -> Option<BTreeMap<PathBuf, Vec<ProjectDef>>> {
+    let mut entries: Vec<(PathBuf, ProjectDef)> = projects
+        .into_iter()
+        .flat_map(|(root, types)| types.into_iter().map(move |t| (root.clone(), t)))
+        .collect();
+    if let Some(only) = only {
+        let wanted: Vec<String> = only.iter().map(|t| type_key(t)).collect();
+        entries.retain(|(_, t)| wanted.contains(&type_key(&t.name)));
+        if entries.is_empty() {
+            if !quiet {
+                println!(
+                    "None of the requested types ({}) were detected.",
+                    only.join(", ")
+                );
+            }
+            return None;
+        }
+    } else if !quiet && entries.len() > 1 && io::stdin().is_terminal() {
+        let mut items: Vec<Item> = entries
+            .iter()
+            .map(|(root, t)| {
+                let (count, bytes) = files
+                    .iter()
+                    .filter(|f| Path::new(f).starts_with(root))
+                    .filter(|f| t.include.iter().any(|p| p.matches(f)))
+                    .fold((0, 0), |(count, bytes), f| {
+                        let size = std::fs::metadata(f).map(|m| m.len()).unwrap_or(0);
+                        (count + 1, bytes + size)
+                    });
+                let name = if root == Path::new(dir) {
+                    t.name.clone()
+                } else {
+                    format!("{} ({})", t.name, root.display())
+                };
+                Item {
+                    label: format!(
+                        "{:<30} {:>5} files {:>10}",
+                        name,
+                        count,
+                        census::format_size(bytes)
+                    ),
+                    ticked: true,
+                }
+            })
+            .collect();
+        let confirmed = picker::pick("Select project types to include", &mut items, &[])
+            .unwrap_or_else(|e| {
+                eprintln!("Error running the type picker: {}", e);
+                exit(1)
+            });
+        if !confirmed {
+            return None;
+        }
+        entries = entries
+            .into_iter()
+            .zip(items)
+            .filter_map(|(entry, item)| item.ticked.then_some(entry))
+            .collect();
+        if entries.is_empty() {
+            return None;
+        }
+    }
+    let mut projects: BTreeMap<PathBuf, Vec<ProjectDef>> = BTreeMap::new();
+    for (root, t) in entries {
+        projects.entry(root).or_default().push(t);
+    }
+    Some(projects)
+}
+
+/// Normalises a type name for comparison, so `c++`, `Cpp` and `cpp` are equal
+fn type_key(name: &str) -> String {
+    name.to_lowercase()
+        .replace('#', "sharp")
+        .replace('+', "p")
+        .replace(['.', ' ', '-'], "")
 }
 
-fn get_project_types(files: &[String]) -> Option<Vec<ProjectType>> {
-    let mut types = Vec::new();
+/// Project roots among `files` mapped to the types whose markers they hold,
+/// printed unless `quiet`
+fn get_project_types(
+    dir: &str,
+    files: &[String],
+    defs: &[ProjectDef],
+    quiet: bool,
+) -> Option<BTreeMap<PathBuf, Vec<ProjectDef>>> {
+    let mut projects: BTreeMap<PathBuf, Vec<ProjectDef>> = BTreeMap::new();
+    let mut add = |root: &Path, name: &str, is_dir: bool| {
+        for def in defs
+            .iter()
+            .filter(|d| d.markers.iter().any(|m| m.matches(name, is_dir)))
+        {
+            let types = projects.entry(root.to_path_buf()).or_default();
+            if !types.iter().any(|t| t.name == def.name) {
+                types.push(def.clone());
+            }
+        }
+    };
+    // directory markers are found through the files they contain
+    let mut seen_dirs = HashSet::new();
     for file in files {
-        if let Some(name) = file.rsplit('/').next() {
-            types.extend(ProjectType::from(name));
+        let path = Path::new(file);
+        let (Some(name), Some(root)) = (path.file_name(), path.parent()) else {
+            continue;
+        };
+        add(root, &name.to_string_lossy(), false);
+        for ancestor in path.ancestors().skip(1) {
+            if ancestor == Path::new(dir) || !seen_dirs.insert(ancestor) {
+                break;
+            }
+            let (Some(name), Some(root)) = (ancestor.file_name(), ancestor.parent()) else {
+                break;
+            };
+            add(root, &name.to_string_lossy(), true);
         }
     }
-    types.dedup();
-    if types.is_empty() {
+    if projects.is_empty() {
         return None;
     }
-    println!(
-        "Detected project type(s): {}",
-        types
-            .iter()
-            .map(|t| t.to_string())
-            .collect::<Vec<_>>()
-            .join(", ")
-    );
-    Some(types)
+    if !quiet {
+        println!(
+            "Detected project type(s): {}",
+            projects
+                .iter()
+                .flat_map(|(root, types)| {
+                    types.iter().map(move |t| {
+                        if root == Path::new(dir) {
+                            t.name.clone()
+                        } else {
+                            format!("{} ({})", t.name, root.display())
+                        }
+                    })
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    Some(projects)
 }
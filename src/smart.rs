@@ -1,80 +1,251 @@
-use std::{io, process::Command};
-use walkdir::WalkDir;
+use std::{io, path::Path};
 
-/// Recursively find directories under `root` whose name (case‐insensitive)
-/// contains `query`. Honor `ignore` substrings. First tries `fd` if present,
-/// otherwise falls back to WalkDir.
-pub fn find_directories(root: &str, query: &str, ignore: &[String]) -> io::Result<Vec<String>> {
-    let query_lc = query.to_lowercase();
+use crate::{
+    dir::is_ignored,
+    frecency::Frecency,
+    walk::{walk, Symlinks},
+};
 
-    // 1) Try external `fd` for speed
-    if let Ok(output) = Command::new("fd")
-        .args(&[
-            "--type",
-            "d",
-            "--hidden",
-            "--no-ignore",
-            // glob for substring match
-            "--glob",
-            &format!("*{}*", query),
-            root,
-        ])
-        .output()
-    {
-        if output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let mut dirs: Vec<String> = stdout
-                .lines()
-                .map(str::to_string)
-                .filter(|p| !p.is_empty())
-                .collect();
-            // filter by our ignore list
-            dirs.retain(|p| !ignore.iter().any(|pat| p.contains(pat)));
-            if !dirs.is_empty() {
-                return Ok(dirs);
-            }
+/// Number of ranked matches offered to the user
+const MAX_RESULTS: usize = 30;
+
+/// Score lead a previously chosen directory needs over the runner-up to be
+/// picked without asking
+const DOMINANCE: i64 = 150;
+
+/// What smart search looks for
+#[derive(Clone, Copy)]
+enum Kind {
+    Dir,
+    File,
+}
+
+/// A directory smart search walks, with its own ignore rules
+pub struct Root {
+    pub path: String,
+    /// Substrings of paths to leave out
+    pub ignore: Vec<String>,
+    /// How many levels below `path` to look, unlimited when `None`
+    pub max_depth: Option<usize>,
+    pub symlinks: Symlinks,
+}
+
+/// A directory or file found by smart search with its ranking
+pub struct Match {
+    pub path: String,
+    pub score: i64,
+    /// Frecency of the directory, zero if it was never chosen before
+    pub frecency: f64,
+}
+
+/// Recursively find directories under the `roots` that fuzzily match
+/// `query`, best matches first, boosting directories chosen before according
+/// to `frecency`.
+pub fn find_directories(
+    roots: &[Root],
+    query: &str,
+    frecency: &Frecency,
+) -> io::Result<Vec<Match>> {
+    let mut matches = Vec::new();
+    for root in roots {
+        for d in list_entries(root, Kind::Dir) {
+            let rel = Path::new(&d)
+                .strip_prefix(&root.path)
+                .unwrap_or(Path::new(&d));
+            let Some(score) = score(query, &rel.to_string_lossy()) else {
+                continue;
+            };
+            let frecency = frecency.score(&d);
+            matches.push(Match {
+                score: score + (frecency.ln_1p() * 40.0) as i64,
+                frecency,
+                path: d,
+            });
+        }
+    }
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    matches.truncate(MAX_RESULTS);
+    Ok(matches)
+}
+
+/// Recursively find files under the `roots` whose name fuzzily matches
+/// `query`, best matches first, with the same walker as `find_directories`
+pub fn find_files(roots: &[Root], query: &str) -> io::Result<Vec<Match>> {
+    let mut matches = Vec::new();
+    for root in roots {
+        for f in list_entries(root, Kind::File) {
+            let rel = Path::new(&f)
+                .strip_prefix(&root.path)
+                .unwrap_or(Path::new(&f));
+            let Some(score) = score(query, &rel.to_string_lossy()) else {
+                continue;
+            };
+            matches.push(Match {
+                score,
+                frecency: 0.0,
+                path: f,
+            });
         }
     }
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    matches.truncate(MAX_RESULTS);
+    Ok(matches)
+}
 
-    // 2) Fallback to WalkDir
-    let mut dirs = Vec::new();
-    for entry in WalkDir::new(root)
-        .follow_links(true)
+/// The best match when it was chosen before and clearly outranks the rest
+pub fn dominant(matches: &[Match]) -> Option<&Match> {
+    let top = matches.first()?;
+    let lead = matches.get(1).map_or(i64::MAX, |m| top.score - m.score);
+    (top.frecency > 0.0 && lead >= DOMINANCE).then_some(top)
+}
+
+/// Every directory or file under `root` not matching one of its `ignore`
+/// patterns, down to its depth limit
+fn list_entries(root: &Root, kind: Kind) -> Vec<String> {
+    let skip = |path: &Path, _| is_ignored(&path.to_string_lossy(), &root.ignore);
+    walk(&root.path, root.max_depth, root.symlinks, &skip)
+        .unwrap_or_default()
         .into_iter()
-        .filter_map(Result::ok)
-    {
-        let path = entry.path();
-        if !path.is_dir() {
-            continue;
+        .filter(|e| match kind {
+            Kind::Dir => e.is_dir,
+            Kind::File => !e.is_dir,
+        })
+        .map(|e| e.path)
+        .collect()
+}
+
+/// Ranks `path` (relative to the search root) against `query`. Matches in the
+/// directory name beat matches spread over the path; shorter and shallower
+/// paths win ties. `None` when the query is not a subsequence of the path.
+fn score(query: &str, path: &str) -> Option<i64> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let depth = path.matches('/').count() as i64;
+    let query_lc = query.to_lowercase();
+    let base = match fuzzy(&query_lc, name) {
+        Some(s) => {
+            let name_lc = name.to_lowercase();
+            let exact = if name_lc == query_lc {
+                50
+            } else if name_lc.contains(&query_lc) {
+                20
+            } else {
+                0
+            };
+            s + 100 + exact
         }
-        let ps = path.to_string_lossy();
-        if ignore.iter().any(|pat| ps.contains(pat)) {
-            continue;
+        None => fuzzy(&query_lc, path)?,
+    };
+    Some(base - depth * 3 - path.chars().count() as i64 / 4)
+}
+
+/// Best score of `query` (lowercase) as a subsequence of `text`, rewarding
+/// consecutive characters and hits at word boundaries
+fn fuzzy(query: &str, text: &str) -> Option<i64> {
+    let query: Vec<char> = query.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let lower: Vec<char> = text
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let bonus = |j: usize| -> i64 {
+        match j.checked_sub(1).map(|p| text[p]) {
+            None => 10,
+            Some('/' | '-' | '_' | '.' | ' ') => 8,
+            Some(prev) if prev.is_lowercase() && text[j].is_uppercase() => 6,
+            Some(_) => 1,
         }
-        if let Some(name) = path.file_name().map(|s| s.to_string_lossy().to_lowercase()) {
-            if name.contains(&query_lc) {
-                dirs.push(ps.to_string());
+    };
+
+    // prev[j]: best score with the previous query character matched at text[j]
+    let mut prev: Vec<Option<i64>> = vec![None; text.len()];
+    for (i, qc) in query.iter().enumerate() {
+        let mut cur = vec![None; text.len()];
+        let mut best_before: Option<i64> = None;
+        for j in 0..text.len() {
+            if lower[j] == *qc {
+                let from = if i == 0 {
+                    Some(0)
+                } else {
+                    let consecutive = j.checked_sub(1).and_then(|p| prev[p]).map(|s| s + 5);
+                    best_before.max(consecutive)
+                };
+                cur[j] = from.map(|s| s + bonus(j));
             }
+            best_before = best_before.max(prev[j]);
         }
+        prev = cur;
     }
-    Ok(dirs)
+    prev.into_iter().flatten().max()
 }
 
-/// Prompt the user to pick one of the `dirs` by number.
-pub fn choose_directory(dirs: &[String]) -> Option<String> {
+/// Prompt the user to pick one or more of the `matches` by number, e.g. `2`,
+/// `1,3,5-7` or `all`. Each match is listed with the line `describe`
+/// returns for it.
+pub fn choose_matches(
+    what: &str,
+    matches: &[Match],
+    describe: &dyn Fn(&str) -> String,
+) -> Option<Vec<String>> {
     use std::io::{self, Write};
-    println!("\nFound {} matching directories:", dirs.len());
-    for (i, d) in dirs.iter().enumerate() {
-        println!("  {}) {}", i + 1, d);
+    println!("\nFound {} matching {}:", matches.len(), what);
+    let width = matches
+        .iter()
+        .map(|d| d.path.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (i, d) in matches.iter().enumerate() {
+        println!(
+            "  {:>2}) {:<width$}  [{}]",
+            i + 1,
+            d.path,
+            describe(&d.path),
+            width = width
+        );
     }
-    print!("\nEnter choice number: ");
+    print!("\nEnter choice (e.g. 2, 1,3,5-7 or all): ");
     io::stdout().flush().ok()?;
     let mut buf = String::new();
     io::stdin().read_line(&mut buf).ok()?;
-    let n = buf.trim().parse::<usize>().ok()?;
-    if n == 0 || n > dirs.len() {
-        None
-    } else {
-        Some(dirs[n - 1].clone())
+    let picked = parse_selection(&buf, matches.len())?;
+    Some(
+        picked
+            .into_iter()
+            .map(|i| matches[i].path.clone())
+            .collect(),
+    )
+}
+
+/// Turns a selection of 1-based numbers and ranges (`1,3,5-7`) or `all` into
+/// 0-based indices below `len`. `None` when anything is malformed or out of
+/// range.
+pub fn parse_selection(spec: &str, len: usize) -> Option<Vec<usize>> {
+    let spec = spec.trim();
+    if spec == "all" {
+        return Some((0..len).collect());
+    }
+    let mut picked = Vec::new();
+    for part in spec.split([',', ' ']).filter(|p| !p.trim().is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((a, b)) => (
+                a.trim().parse::<usize>().ok()?,
+                b.trim().parse::<usize>().ok()?,
+            ),
+            None => {
+                let n = part.trim().parse::<usize>().ok()?;
+                (n, n)
+            }
+        };
+        if start == 0 || start > end || end > len {
+            return None;
+        }
+        for n in start..=end {
+            if !picked.contains(&(n - 1)) {
+                picked.push(n - 1);
+            }
+        }
     }
+    (!picked.is_empty()).then_some(picked)
 }
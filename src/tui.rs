@@ -0,0 +1,415 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crossterm::{
+    cursor,
+    event::{KeyCode, KeyModifiers},
+    queue,
+    style::Print,
+    terminal::{self, ClearType},
+};
+
+use crate::{
+    census::format_size,
+    picker::{self, Key},
+};
+
+/// A file or directory row of the browser
+struct Entry {
+    path: String,
+    name: String,
+    depth: usize,
+    is_dir: bool,
+    size: u64,
+    /// One past the last descendant, for directories
+    end: usize,
+    expanded: bool,
+    selected: bool,
+}
+
+struct Browser<'a> {
+    entries: Vec<Entry>,
+    /// Position in the visible rows
+    cursor: usize,
+    offset: usize,
+    filter: String,
+    searching: bool,
+    read: &'a dyn Fn(&str) -> String,
+    /// Entry index and lines of the last preview
+    preview: Option<(usize, Vec<String>)>,
+}
+
+/// Opens a full-screen tree of `files` under `dir` with `preselected` ticked,
+/// and returns the files ticked on Enter, or `None` when cancelled. `read`
+/// provides file contents for the preview pane.
+pub fn browse(
+    dir: &str,
+    files: &[String],
+    preselected: &[String],
+    read: &dyn Fn(&str) -> String,
+) -> io::Result<Option<Vec<String>>> {
+    let mut browser = Browser {
+        entries: build_tree(dir, files, preselected),
+        cursor: 0,
+        offset: 0,
+        filter: String::new(),
+        searching: false,
+        read,
+        preview: None,
+    };
+    let confirmed = picker::full_screen(|out| browser.run(out))?;
+    Ok(confirmed.then(|| {
+        browser
+            .entries
+            .into_iter()
+            .filter(|e| !e.is_dir && e.selected)
+            .map(|e| e.path)
+            .collect()
+    }))
+}
+
+/// Lays `files` out depth-first, each directory directly followed by its contents
+fn build_tree(dir: &str, files: &[String], preselected: &[String]) -> Vec<Entry> {
+    let root = Path::new(dir);
+    let preselected: HashSet<&String> = preselected.iter().collect();
+    let mut files: Vec<(PathBuf, &String)> = files
+        .iter()
+        .map(|f| {
+            let rel = Path::new(f).strip_prefix(root).unwrap_or(Path::new(f));
+            (rel.to_path_buf(), f)
+        })
+        .collect();
+    files.sort_by(|a, b| a.0.components().cmp(b.0.components()));
+
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut dirs: HashMap<PathBuf, usize> = HashMap::new();
+    for (rel, file) in files {
+        let mut prefix = PathBuf::new();
+        let parents: Vec<_> = rel
+            .parent()
+            .into_iter()
+            .flat_map(|p| p.components())
+            .collect();
+        for (depth, comp) in parents.iter().enumerate() {
+            prefix.push(comp);
+            if dirs.contains_key(&prefix) {
+                continue;
+            }
+            dirs.insert(prefix.clone(), entries.len());
+            entries.push(Entry {
+                path: root.join(&prefix).to_string_lossy().to_string(),
+                name: comp.as_os_str().to_string_lossy().to_string(),
+                depth,
+                is_dir: true,
+                size: 0,
+                end: 0,
+                expanded: false,
+                selected: false,
+            });
+        }
+        entries.push(Entry {
+            path: file.clone(),
+            name: rel
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            depth: parents.len(),
+            is_dir: false,
+            size: fs::metadata(file).map(|m| m.len()).unwrap_or(0),
+            end: 0,
+            expanded: false,
+            selected: preselected.contains(file),
+        });
+    }
+
+    for i in 0..entries.len() {
+        let mut end = i + 1;
+        if entries[i].is_dir {
+            while end < entries.len() && entries[end].depth > entries[i].depth {
+                end += 1;
+            }
+        }
+        entries[i].end = end;
+    }
+    // open the directories holding what auto-detection picked
+    for i in 0..entries.len() {
+        if entries[i].is_dir {
+            let picked = entries[i + 1..entries[i].end].iter().any(|e| e.selected);
+            entries[i].expanded = picked;
+        }
+    }
+    entries
+}
+
+impl Browser<'_> {
+    fn run(&mut self, out: &mut impl Write) -> io::Result<bool> {
+        loop {
+            self.draw(out)?;
+            let key = picker::next_key()?;
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                return Ok(false);
+            }
+            let rows = self.visible();
+            if self.searching {
+                match key.code {
+                    KeyCode::Char(c) => self.filter.push(c),
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                    }
+                    KeyCode::Esc => {
+                        self.filter.clear();
+                        self.searching = false;
+                    }
+                    KeyCode::Enter => self.searching = false,
+                    _ => {}
+                }
+                self.cursor = 0;
+                continue;
+            }
+            let current = rows.get(self.cursor).copied();
+            let key = match picker::navigate(key, self.cursor, rows.len(), self.page()) {
+                Key::Moved(to) => {
+                    self.cursor = to;
+                    continue;
+                }
+                Key::Cancel => return Ok(false),
+                Key::Other(key) => key,
+            };
+            match key.code {
+                KeyCode::Char(' ') => {
+                    if let Some(i) = current {
+                        self.toggle(i);
+                    }
+                }
+                KeyCode::Char('a') => {
+                    let files: Vec<usize> = rows
+                        .into_iter()
+                        .filter(|&i| !self.entries[i].is_dir)
+                        .collect();
+                    let tick = files.iter().any(|&i| !self.entries[i].selected);
+                    files
+                        .into_iter()
+                        .for_each(|i| self.entries[i].selected = tick);
+                }
+                KeyCode::Right | KeyCode::Char('l') => {
+                    if let Some(i) = current.filter(|&i| self.entries[i].is_dir) {
+                        self.entries[i].expanded = true;
+                    }
+                }
+                KeyCode::Left | KeyCode::Char('h') => {
+                    if let Some(i) = current {
+                        if self.entries[i].is_dir && self.entries[i].expanded {
+                            self.entries[i].expanded = false;
+                        } else if let Some(parent) = self.parent(i) {
+                            self.entries[parent].expanded = false;
+                            self.cursor = self
+                                .visible()
+                                .iter()
+                                .position(|&r| r == parent)
+                                .unwrap_or(0);
+                        }
+                    }
+                }
+                KeyCode::Char('/') => self.searching = true,
+                KeyCode::Enter => return Ok(true),
+                _ => {}
+            }
+        }
+    }
+
+    /// Indices of the entries shown, honoring collapsed directories and the filter
+    fn visible(&self) -> Vec<usize> {
+        let filter = self.filter.to_lowercase();
+        let matches = |e: &Entry| filter.is_empty() || e.path.to_lowercase().contains(&filter);
+        let mut rows = Vec::new();
+        let mut i = 0;
+        while i < self.entries.len() {
+            let e = &self.entries[i];
+            if !e.is_dir {
+                if matches(e) {
+                    rows.push(i);
+                }
+                i += 1;
+                continue;
+            }
+            let any = self.entries[i + 1..e.end]
+                .iter()
+                .any(|d| !d.is_dir && matches(d));
+            if !any {
+                i = e.end;
+                continue;
+            }
+            rows.push(i);
+            // a filter shows every match, however deep
+            i = if e.expanded || !filter.is_empty() {
+                i + 1
+            } else {
+                e.end
+            };
+        }
+        rows
+    }
+
+    fn parent(&self, i: usize) -> Option<usize> {
+        let depth = self.entries[i].depth;
+        (0..i)
+            .rev()
+            .find(|&p| self.entries[p].is_dir && self.entries[p].depth < depth)
+    }
+
+    fn toggle(&mut self, i: usize) {
+        let (start, end) = (i, self.entries[i].end);
+        if !self.entries[i].is_dir {
+            self.entries[i].selected = !self.entries[i].selected;
+            return;
+        }
+        let tick = self.entries[start + 1..end]
+            .iter()
+            .any(|e| !e.is_dir && !e.selected);
+        for e in &mut self.entries[start + 1..end] {
+            e.selected = tick;
+        }
+    }
+
+    fn page(&self) -> usize {
+        let (_, rows) = picker::screen_size();
+        rows.saturating_sub(3).max(1)
+    }
+
+    fn preview(&mut self, i: usize, height: usize) -> &[String] {
+        if self.preview.as_ref().map(|(p, _)| *p) != Some(i) {
+            let e = &self.entries[i];
+            let lines = if e.is_dir {
+                let files: Vec<&Entry> = self.entries[i + 1..e.end]
+                    .iter()
+                    .filter(|d| !d.is_dir)
+                    .collect();
+                let bytes: u64 = files.iter().map(|f| f.size).sum();
+                vec![
+                    e.path.clone(),
+                    String::new(),
+                    format!("{} files, {}", files.len(), format_size(bytes)),
+                ]
+            } else {
+                (self.read)(&e.path)
+                    .lines()
+                    .take(height)
+                    .map(|l| l.replace('\t', "    "))
+                    .collect()
+            };
+            self.preview = Some((i, lines));
+        }
+        self.preview
+            .as_ref()
+            .map(|(_, l)| l.as_slice())
+            .unwrap_or(&[])
+    }
+
+    fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let (cols, rows) = picker::screen_size();
+        let height = rows.saturating_sub(2).max(1);
+        let left = cols / 2;
+        let right = cols.saturating_sub(left + 3);
+
+        let visible = self.visible();
+        self.cursor = self.cursor.min(visible.len().saturating_sub(1));
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + height {
+            self.offset = self.cursor + 1 - height;
+        }
+
+        let (count, bytes) = self
+            .entries
+            .iter()
+            .filter(|e| !e.is_dir && e.selected)
+            .fold((0, 0), |(c, b), e| (c + 1, b + e.size));
+        let status = format!(
+            "{} files  {}  ~{} tokens",
+            count,
+            format_size(bytes),
+            bytes / 4
+        );
+        let help = if self.searching {
+            format!("/{}_", self.filter)
+        } else if self.filter.is_empty() {
+            "space toggle  ←/→ collapse/expand  / search  a all  Enter bundle  Esc cancel".into()
+        } else {
+            format!("filter: {}  (/ to edit)", self.filter)
+        };
+        queue!(
+            out,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0),
+            Print(fit(&status, cols)),
+            cursor::MoveTo(0, 1),
+            Print(fit(&help, cols)),
+        )?;
+
+        let preview: Vec<String> = match visible.get(self.cursor) {
+            Some(&i) => self.preview(i, height).to_vec(),
+            None => Vec::new(),
+        };
+        for row in 0..height {
+            let tree = match visible.get(self.offset + row) {
+                Some(&i) => self.line(i, self.offset + row == self.cursor),
+                None => String::new(),
+            };
+            let text = preview.get(row).map(String::as_str).unwrap_or("");
+            queue!(
+                out,
+                cursor::MoveTo(0, (row + 2) as u16),
+                Print(fit(&tree, left)),
+                Print(" │ "),
+                Print(fit(text, right)),
+            )?;
+        }
+        out.flush()
+    }
+
+    fn line(&self, i: usize, current: bool) -> String {
+        let e = &self.entries[i];
+        let check = if e.is_dir {
+            let files = self.entries[i + 1..e.end].iter().filter(|d| !d.is_dir);
+            let (all, any) = files.fold((true, false), |(all, any), d| {
+                (all && d.selected, any || d.selected)
+            });
+            if all {
+                'x'
+            } else if any {
+                '-'
+            } else {
+                ' '
+            }
+        } else if e.selected {
+            'x'
+        } else {
+            ' '
+        };
+        let arrow = match (e.is_dir, e.expanded || !self.filter.is_empty()) {
+            (false, _) => "  ",
+            (true, true) => "▾ ",
+            (true, false) => "▸ ",
+        };
+        format!(
+            "{}{}{}[{}] {}",
+            if current { '>' } else { ' ' },
+            "  ".repeat(e.depth),
+            arrow,
+            check,
+            e.name
+        )
+    }
+}
+
+/// Cuts or pads `s` to exactly `width` characters
+fn fit(s: &str, width: usize) -> String {
+    let mut line: String = s.chars().take(width).collect();
+    let len = line.chars().count();
+    line.extend(std::iter::repeat_n(' ', width - len));
+    line
+}
@@ -0,0 +1,200 @@
+use std::{
+    fs::{self, read_dir},
+    io,
+    path::{Path, PathBuf},
+    sync::{Condvar, Mutex},
+    thread,
+};
+
+/// How symbolic links met while walking are treated
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Symlinks {
+    /// Keep links whose target lies inside the walked root and skip the rest.
+    /// Linked directories are listed but not entered, their targets being
+    /// walked under their real path anyway.
+    #[default]
+    Inside,
+    /// Follow every link
+    Follow,
+    /// Leave out every link
+    Never,
+}
+
+/// What identifies a directory however it is reached: device and inode
+#[cfg(unix)]
+type DirId = (u64, u64);
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+#[cfg(unix)]
+fn dir_id(path: &Path) -> Option<DirId> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(path: &Path) -> Option<DirId> {
+    fs::canonicalize(path).ok()
+}
+
+/// A file or directory found while walking
+pub struct Entry {
+    pub path: String,
+    pub is_dir: bool,
+}
+
+/// A directory waiting to be read
+struct Job {
+    dir: PathBuf,
+    /// Depth below the root
+    depth: usize,
+    /// The directories above it and itself, so `Symlinks::Follow` can tell a
+    /// link back to one of them from a fresh directory
+    ancestors: Vec<DirId>,
+}
+
+/// Work shared by the walking threads
+struct State {
+    queue: Vec<Job>,
+    /// Threads currently reading a directory
+    busy: usize,
+    found: Vec<Entry>,
+}
+
+/// Settings every walking thread shares
+struct Walk<'a> {
+    max_depth: Option<usize>,
+    symlinks: Symlinks,
+    /// Canonical path of the root, which `Symlinks::Inside` keeps links in
+    root: PathBuf,
+    skip: &'a (dyn Fn(&Path, bool) -> bool + Sync),
+}
+
+/// Lists everything below `root` on all cores, sorted by path. Entries for
+/// which `skip` returns true are left out along with everything under them.
+/// `max_depth` limits how many levels are read, `Some(1)` meaning only the
+/// children of `root`. Links are handled as `symlinks` says; when they are
+/// followed, a link back to a directory being walked is not entered again.
+pub fn walk(
+    root: &str,
+    max_depth: Option<usize>,
+    symlinks: Symlinks,
+    skip: &(dyn Fn(&Path, bool) -> bool + Sync),
+) -> io::Result<Vec<Entry>> {
+    // report an unreadable root instead of returning nothing
+    read_dir(root)?;
+    let walk = Walk {
+        max_depth,
+        symlinks,
+        root: fs::canonicalize(root)?,
+        skip,
+    };
+    let state = Mutex::new(State {
+        queue: vec![Job {
+            dir: PathBuf::from(root),
+            depth: 0,
+            ancestors: dir_id(Path::new(root)).into_iter().collect(),
+        }],
+        busy: 0,
+        found: Vec::new(),
+    });
+    let ready = Condvar::new();
+    let threads = thread::available_parallelism().map_or(4, |n| n.get());
+    thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| worker(&state, &ready, &walk));
+        }
+    });
+    let mut found = state.into_inner().unwrap_or_else(|e| e.into_inner()).found;
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(found)
+}
+
+/// Takes directories off the queue until every thread runs out of work
+fn worker(state: &Mutex<State>, ready: &Condvar, walk: &Walk) {
+    loop {
+        let job = {
+            let mut st = state.lock().unwrap_or_else(|e| e.into_inner());
+            loop {
+                if let Some(job) = st.queue.pop() {
+                    st.busy += 1;
+                    break job;
+                }
+                if st.busy == 0 {
+                    ready.notify_all();
+                    return;
+                }
+                st = ready.wait(st).unwrap_or_else(|e| e.into_inner());
+            }
+        };
+
+        let mut found = Vec::new();
+        let mut subdirs = Vec::new();
+        // unreadable directories below the root are skipped
+        for entry in read_dir(&job.dir)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+        {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let is_link = file_type.is_symlink();
+            // only links need their target looked up
+            let is_dir = if is_link {
+                let keep = match walk.symlinks {
+                    Symlinks::Follow => true,
+                    Symlinks::Inside => {
+                        fs::canonicalize(&path).is_ok_and(|target| target.starts_with(&walk.root))
+                    }
+                    Symlinks::Never => false,
+                };
+                if !keep {
+                    continue;
+                }
+                path.is_dir()
+            } else {
+                file_type.is_dir()
+            };
+            if (walk.skip)(&path, is_dir) {
+                continue;
+            }
+            let enter = !is_link || walk.symlinks == Symlinks::Follow;
+            if is_dir && enter && walk.max_depth.is_none_or(|max| job.depth + 1 < max) {
+                subdirs.push(path.clone());
+            }
+            found.push(Entry {
+                path: path.to_string_lossy().to_string(),
+                is_dir,
+            });
+        }
+
+        let mut jobs = Vec::new();
+        for sub in subdirs {
+            let mut ancestors = Vec::new();
+            if walk.symlinks == Symlinks::Follow {
+                let Some(id) = dir_id(&sub) else {
+                    continue;
+                };
+                if job.ancestors.contains(&id) {
+                    continue;
+                }
+                ancestors = job.ancestors.clone();
+                ancestors.push(id);
+            }
+            jobs.push(Job {
+                dir: sub,
+                depth: job.depth + 1,
+                ancestors,
+            });
+        }
+
+        let mut st = state.lock().unwrap_or_else(|e| e.into_inner());
+        st.queue.extend(jobs);
+        st.found.extend(found);
+        st.busy -= 1;
+        ready.notify_all();
+    }
+}